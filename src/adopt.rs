@@ -0,0 +1,245 @@
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::{Config, ForkPlacement};
+use crate::discovery::Walker;
+use crate::identity;
+use crate::layout::Layout;
+use crate::path;
+use crate::relocate;
+use crate::repo;
+
+/// Where an existing clone belongs, or why it cannot go there.
+#[derive(Debug, PartialEq)]
+enum Plan {
+    Move { repo_path: String, target: PathBuf },
+    InPlace,
+    Conflict(String),
+}
+
+/// Move clones living outside the src root to the location their `origin`
+/// maps to, re-linking their worktrees.
+///
+/// `scan` adds every repository found under that directory to `paths`.
+pub fn adopt(config: &Config, paths: &[String], scan: Option<&str>, dry_run: bool) -> Result<()> {
+    let layout = Layout::new(config)?;
+
+    let mut candidates = Vec::new();
+    for path in paths {
+        let dir =
+            fs::canonicalize(path).with_context(|| format!("Repository not found: {}", path))?;
+        if !dir.join(".git").exists() {
+            anyhow::bail!("Not a git repository: {}", dir.display());
+        }
+        candidates.push(dir);
+    }
+
+    let scan_dir = scan
+        .map(|dir| fs::canonicalize(dir).with_context(|| format!("Directory not found: {}", dir)))
+        .transpose()?;
+    if let Some(ref dir) = scan_dir {
+        // Linked worktrees found by the walk move with their repository.
+        let repos = Walker::new(config).walk(dir).repos;
+        candidates.extend(repos.into_iter().filter(|repo| repo.join(".git").is_dir()));
+    }
+
+    if candidates.is_empty() {
+        anyhow::bail!("No repositories to adopt.\nGive one or more paths, or --scan <dir>");
+    }
+
+    let plans = plan(
+        &layout,
+        config.fork_placement,
+        config.remote_name(),
+        &candidates,
+    );
+    let mut moved = 0;
+    let mut conflicts = 0;
+
+    for (dir, plan) in candidates.iter().zip(plans) {
+        match plan {
+            Plan::InPlace => {}
+            Plan::Conflict(reason) => {
+                println!("Refusing {}: {}", dir.display(), reason);
+                conflicts += 1;
+            }
+            Plan::Move { repo_path, target } => {
+                println!(
+                    "{} -> {}",
+                    dir.display(),
+                    layout.relative_path(&repo_path)?.display()
+                );
+                moved += 1;
+
+                if dry_run {
+                    continue;
+                }
+
+                // Worktrees outside the clone stay where they are; only the
+                // links between them and the repository are repaired.
+                let worktrees = layout.worktree_dir(&repo_path)?;
+                relocate::relocate(dir, &target, &worktrees, &worktrees)?;
+                identity::apply(config, &target, Some(&repo_path))?;
+                if let Some(ref scan_dir) = scan_dir {
+                    relocate::remove_empty_parents(dir, scan_dir);
+                }
+            }
+        }
+    }
+
+    if dry_run {
+        println!(
+            "{} repositories would be adopted, {} refused",
+            moved, conflicts
+        );
+    } else {
+        println!("Adopted {} repositories, {} refused", moved, conflicts);
+    }
+
+    if conflicts > 0 {
+        anyhow::bail!("{} repositories could not be adopted", conflicts);
+    }
+
+    Ok(())
+}
+
+fn plan(
+    layout: &Layout,
+    placement: ForkPlacement,
+    remote: &str,
+    candidates: &[PathBuf],
+) -> Vec<Plan> {
+    let mut claimed: HashMap<PathBuf, &Path> = HashMap::new();
+
+    candidates
+        .iter()
+        .map(|dir| {
+            if dir.join(".git").is_file() {
+                return Plan::Conflict("a linked worktree; adopt its repository".to_string());
+            }
+
+            let repo_path = match repo::placement_url(dir, placement, remote)
+                .and_then(|url| path::parse_repo_path(&url))
+            {
+                Ok(repo_path) => repo_path,
+                Err(_) => return Plan::Conflict(format!("no parsable '{}' remote", remote)),
+            };
+
+            let target = match layout.repo_dir(&repo_path) {
+                Ok(target) => target,
+                Err(e) => return Plan::Conflict(e.to_string()),
+            };
+
+            if target.exists() {
+                return match fs::canonicalize(&target) {
+                    Ok(existing) if existing == *dir => Plan::InPlace,
+                    _ => Plan::Conflict(format!("{} already exists", target.display())),
+                };
+            }
+
+            if target.starts_with(dir) {
+                return Plan::Conflict(format!("{} is inside it", target.display()));
+            }
+
+            if let Some(other) = claimed.get(&target) {
+                return Plan::Conflict(format!(
+                    "{} has the same origin and is adopted first",
+                    other.display()
+                ));
+            }
+            claimed.insert(target.clone(), dir);
+
+            Plan::Move { repo_path, target }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::{git_output, run_git};
+    use crate::layout::DEFAULT_TEMPLATE;
+    use crate::test_support::{init_repo, temp_dir};
+
+    fn clone_with_origin(dir: &Path, origin: &str) {
+        init_repo(dir, &["a.txt"]);
+        run_git(dir, &["remote", "add", "origin", origin]).unwrap();
+    }
+
+    #[test]
+    fn test_plan_and_relocate_with_worktree() {
+        let tmp = temp_dir("adopt");
+        let layout = Layout::with_template(tmp.join("src"), DEFAULT_TEMPLATE).unwrap();
+
+        let work = tmp.join("work/app");
+        clone_with_origin(&work, "git@github.com:org/app.git");
+        let feature = tmp.join("work/app-feature");
+        run_git(
+            &work,
+            &[
+                "worktree",
+                "add",
+                "--quiet",
+                "-b",
+                "feature",
+                feature.to_str().unwrap(),
+            ],
+        )
+        .unwrap();
+
+        let duplicate = tmp.join("code/app");
+        clone_with_origin(&duplicate, "https://github.com/org/app");
+        let local = tmp.join("code/local");
+        init_repo(&local, &["a.txt"]);
+
+        let target = tmp.join("src/github.com/org/app");
+        let plans = plan(
+            &layout,
+            ForkPlacement::Upstream,
+            "origin",
+            &[work.clone(), duplicate.clone(), local],
+        );
+        assert_eq!(
+            plans[0],
+            Plan::Move {
+                repo_path: "github.com/org/app".to_string(),
+                target: target.clone()
+            }
+        );
+        assert!(matches!(plans[1], Plan::Conflict(_)));
+        assert!(matches!(plans[2], Plan::Conflict(_)));
+
+        let worktrees = layout.worktree_dir("github.com/org/app").unwrap();
+        relocate::relocate(&work, &target, &worktrees, &worktrees).unwrap();
+        assert!(!work.exists());
+        assert_eq!(
+            git_output(&feature, &["rev-parse", "--show-toplevel"]).unwrap(),
+            feature.display().to_string()
+        );
+        let list = git_output(&target, &["worktree", "list", "--porcelain"]).unwrap();
+        assert!(list.contains(&feature.display().to_string()));
+        assert!(!list.contains("prunable"));
+
+        assert_eq!(
+            plan(
+                &layout,
+                ForkPlacement::Upstream,
+                "origin",
+                std::slice::from_ref(&target)
+            ),
+            vec![Plan::InPlace]
+        );
+        assert!(matches!(
+            plan(&layout, ForkPlacement::Upstream, "origin", &[duplicate])[0],
+            Plan::Conflict(_)
+        ));
+        assert!(matches!(
+            plan(&layout, ForkPlacement::Upstream, "origin", &[feature])[0],
+            Plan::Conflict(_)
+        ));
+
+        fs::remove_dir_all(&tmp).ok();
+    }
+}
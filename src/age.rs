@@ -0,0 +1,135 @@
+use anyhow::Result;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MINUTE: u64 = 60;
+const HOUR: u64 = 60 * MINUTE;
+const DAY: u64 = 24 * HOUR;
+const WEEK: u64 = 7 * DAY;
+const MONTH: u64 = 30 * DAY;
+const YEAR: u64 = 365 * DAY;
+
+/// Seconds since the Unix epoch.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Compact age such as `45s`, `3h`, `2w` or `1y`.
+pub fn format_age(secs: u64) -> String {
+    const UNITS: &[(u64, &str)] = &[
+        (YEAR, "y"),
+        (MONTH, "mo"),
+        (WEEK, "w"),
+        (DAY, "d"),
+        (HOUR, "h"),
+        (MINUTE, "m"),
+    ];
+
+    UNITS
+        .iter()
+        .find(|(unit, _)| secs >= *unit)
+        .map(|(unit, suffix)| format!("{}{}", secs / unit, suffix))
+        .unwrap_or_else(|| format!("{}s", secs))
+}
+
+/// UTC timestamp usable in file names, e.g. `20261018-093005`.
+pub fn format_timestamp(secs: u64) -> String {
+    let (year, month, day) = civil_date(secs);
+    let time = secs % DAY;
+
+    format!(
+        "{:04}{:02}{:02}-{:02}{:02}{:02}",
+        year,
+        month,
+        day,
+        time / HOUR,
+        time % HOUR / MINUTE,
+        time % MINUTE
+    )
+}
+
+/// The UTC year of a timestamp.
+pub fn year(secs: u64) -> i64 {
+    civil_date(secs).0
+}
+
+/// Days since the epoch to a civil date (Howard Hinnant's algorithm).
+fn civil_date(secs: u64) -> (i64, i64, i64) {
+    let days = (secs / DAY) as i64;
+
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
+
+/// Parse an age like `30d`, `12h` or `2w` into seconds.
+pub fn parse_age(value: &str) -> Result<u64> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+
+    let unit = match unit {
+        "s" => 1,
+        "m" => MINUTE,
+        "h" => HOUR,
+        "d" => DAY,
+        "w" => WEEK,
+        "mo" => MONTH,
+        "y" => YEAR,
+        _ => anyhow::bail!(
+            "Invalid age: {}\n\
+            Expected a number followed by s, m, h, d, w, mo or y (e.g., 30d)",
+            value
+        ),
+    };
+
+    match number.parse::<u64>().ok().and_then(|n| n.checked_mul(unit)) {
+        Some(age) => Ok(age),
+        None => anyhow::bail!("Invalid age: {}", value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(5), "5s");
+        assert_eq!(format_age(90), "1m");
+        assert_eq!(format_age(3 * 3600), "3h");
+        assert_eq!(format_age(15 * 24 * 3600), "2w");
+        assert_eq!(format_age(400 * 24 * 3600), "1y");
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0), "19700101-000000");
+        assert_eq!(format_timestamp(951_782_400), "20000229-000000");
+        assert_eq!(format_timestamp(1_792_309_447), "20261018-074407");
+        assert_eq!(year(1_792_309_447), 2026);
+    }
+
+    #[test]
+    fn test_parse_age() {
+        assert_eq!(parse_age("30d").unwrap(), 30 * DAY);
+        assert_eq!(parse_age("12h").unwrap(), 12 * HOUR);
+        assert_eq!(parse_age("2mo").unwrap(), 2 * MONTH);
+        assert!(parse_age("30").is_err());
+        assert!(parse_age("d").is_err());
+        assert!(parse_age("3 days").is_err());
+        assert!(parse_age("999999999999999y").is_err());
+    }
+}
@@ -0,0 +1,618 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::age;
+use crate::config::{Config, ForkPlacement};
+use crate::git::{git_output, run_git};
+use crate::layout::Layout;
+use crate::path;
+use crate::relocate;
+use crate::repo::UPSTREAM_REMOTE;
+use crate::repository;
+use crate::safety::SafetyReport;
+use crate::worktree::Worktree;
+
+const BUNDLE_FILE: &str = "repo.bundle";
+const METADATA_FILE: &str = "archive.toml";
+
+/// Refs created for the archive only, so the bundle carries stashes and
+/// detached checkouts.
+const ARCHIVE_REFS: &str = "refs/sonic-archive";
+
+/// Local settings `git init` writes itself, or that describe the old
+/// checkout rather than the repository.
+const SKIPPED_CONFIG: &[&str] = &[
+    "core.repositoryformatversion",
+    "core.filemode",
+    "core.bare",
+    "core.logallrefupdates",
+    "core.ignorecase",
+    "core.precomposeunicode",
+    "core.symlinks",
+    "core.worktree",
+];
+
+/// Everything besides the bundle that `unarchive` needs to rebuild a
+/// repository.
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct ArchiveMetadata {
+    /// Repository path relative to the src root, as given to `archive`.
+    pub repository: String,
+    pub archived_at: u64,
+    pub bare: bool,
+    /// Branch HEAD pointed to; without one HEAD was detached at `head_commit`.
+    pub head: Option<String>,
+    pub head_commit: Option<String>,
+    #[serde(default, rename = "remote")]
+    pub remotes: Vec<ArchivedRemote>,
+    #[serde(default)]
+    pub config: Vec<ConfigEntry>,
+    #[serde(default, rename = "worktree")]
+    pub worktrees: Vec<ArchivedWorktree>,
+    /// Newest first, as `git stash list` shows them.
+    #[serde(default, rename = "stash")]
+    pub stashes: Vec<ArchivedStash>,
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct ArchivedRemote {
+    pub name: String,
+    pub url: String,
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct ConfigEntry {
+    pub key: String,
+    pub value: String,
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct ArchivedWorktree {
+    pub original_path: PathBuf,
+    pub branch: Option<String>,
+    pub commit: String,
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct ArchivedStash {
+    pub commit: String,
+    pub message: String,
+}
+
+/// One archived repository: `<archive>/<repository>/` holding the bundle
+/// and the metadata file.
+pub struct ArchiveEntry {
+    pub dir: PathBuf,
+    pub metadata: ArchiveMetadata,
+}
+
+impl ArchiveEntry {
+    fn bundle(&self) -> PathBuf {
+        self.dir.join(BUNDLE_FILE)
+    }
+}
+
+/// Where archives are kept: `sonic-git.archiveDir`, or
+/// `<root>/.sonic-archive`.
+fn archive_root(config: &Config, layout: &Layout) -> PathBuf {
+    match config.archive_dir {
+        Some(ref dir) => PathBuf::from(dir),
+        None => layout.root.join(".sonic-archive"),
+    }
+}
+
+/// Bundle a repository with its metadata into the archive, then remove it
+/// and its worktrees.
+pub fn archive(config: &Config, repository: &str, force: bool) -> Result<()> {
+    let layout = Layout::new(config)?;
+    let repo_dir = layout.existing_dir(repository)?;
+    let repository = &repo_dir.strip_prefix(&layout.root)?.display().to_string();
+
+    if !repo_dir.join(".git").exists() {
+        anyhow::bail!("Repository not found: {}", repo_dir.display());
+    }
+
+    let report = SafetyReport::collect(&repo_dir)?;
+    let dirty: Vec<_> = report
+        .worktrees
+        .iter()
+        .filter(|wt| wt.changes > 0)
+        .collect();
+    if !dirty.is_empty() && !force {
+        for wt in &dirty {
+            println!(
+                "  {} ({} uncommitted changes)",
+                wt.path.display(),
+                wt.changes
+            );
+        }
+        anyhow::bail!(
+            "Refusing to archive {}: uncommitted changes are not kept in the archive.\n\
+            Commit or stash them first, or use --force to drop them.",
+            repository
+        );
+    }
+
+    let entry = write_archive(&archive_root(config, &layout), repository, &repo_dir)?;
+
+    let worktree_base = layout.worktree_base();
+    for wt in &entry.metadata.worktrees {
+        if !wt.original_path.exists() {
+            continue;
+        }
+        fs::remove_dir_all(&wt.original_path)
+            .with_context(|| format!("Failed to remove {}", wt.original_path.display()))?;
+        relocate::remove_empty_parents(&wt.original_path, &worktree_base);
+        println!("Removed worktree: {}", wt.original_path.display());
+    }
+    fs::remove_dir_all(&repo_dir)
+        .with_context(|| format!("Failed to remove {}", repo_dir.display()))?;
+    relocate::remove_empty_parents(&repo_dir, &layout.root);
+
+    println!("✓ Archived {} to {}", repository, entry.dir.display());
+    println!(
+        "  Restore with: g sonic-repository unarchive {}",
+        repository
+    );
+    Ok(())
+}
+
+/// Write the bundle and metadata of the repository at `repo_dir` into
+/// `<archive_root>/<repository>/`. The repository itself is left as it was.
+fn write_archive(archive_root: &Path, repository: &str, repo_dir: &Path) -> Result<ArchiveEntry> {
+    let dir = archive_root.join(repository);
+    if dir.exists() {
+        anyhow::bail!(
+            "{} is already archived: {}\n\
+            Unarchive or remove it first.",
+            repository,
+            dir.display()
+        );
+    }
+
+    let metadata = collect_metadata(repository, repo_dir)?;
+    let entry = ArchiveEntry { dir, metadata };
+
+    let result = bundle(&entry, repo_dir);
+    drop_archive_refs(repo_dir);
+    if let Err(e) = result {
+        fs::remove_dir_all(&entry.dir).ok();
+        return Err(e);
+    }
+
+    Ok(entry)
+}
+
+fn collect_metadata(repository: &str, repo_dir: &Path) -> Result<ArchiveMetadata> {
+    let bare = repo_dir.join(".git").is_dir()
+        && git_output(repo_dir, &["rev-parse", "--is-bare-repository"])? == "true";
+
+    let worktrees = Worktree::list(&repo_dir.to_path_buf())?
+        .into_iter()
+        .filter(|wt| !wt.is_bare && wt.path != repo_dir && wt.path.exists())
+        .map(|wt| ArchivedWorktree {
+            original_path: wt.path,
+            branch: wt.branch,
+            commit: wt.head_sha,
+        })
+        .collect();
+
+    let remotes = git_output(repo_dir, &["remote"])?
+        .lines()
+        .filter_map(|name| {
+            let url = git_output(repo_dir, &["remote", "get-url", name]).ok()?;
+            Some(ArchivedRemote {
+                name: name.to_string(),
+                url,
+            })
+        })
+        .collect();
+
+    // `git config -z` separates key and value by a newline, entries by NUL.
+    let config = git_output(repo_dir, &["config", "--local", "--list", "-z"])?
+        .split('\0')
+        .filter_map(|entry| {
+            let (key, value) = entry.split_once('\n').unwrap_or((entry, ""));
+            let skipped =
+                key.is_empty() || SKIPPED_CONFIG.contains(&key) || key.starts_with("extensions.");
+            (!skipped).then(|| ConfigEntry {
+                key: key.to_string(),
+                value: value.to_string(),
+            })
+        })
+        .collect();
+
+    let stashes = git_output(
+        repo_dir,
+        &["reflog", "show", "--format=%H%x09%gs", "refs/stash"],
+    )
+    .unwrap_or_default()
+    .lines()
+    .filter_map(|line| {
+        let (commit, message) = line.split_once('\t')?;
+        Some(ArchivedStash {
+            commit: commit.to_string(),
+            message: message.to_string(),
+        })
+    })
+    .collect();
+
+    Ok(ArchiveMetadata {
+        repository: repository.to_string(),
+        archived_at: age::now(),
+        bare,
+        head: git_output(repo_dir, &["symbolic-ref", "--quiet", "--short", "HEAD"]).ok(),
+        head_commit: git_output(repo_dir, &["rev-parse", "--verify", "--quiet", "HEAD"]).ok(),
+        remotes,
+        config,
+        worktrees,
+        stashes,
+    })
+}
+
+fn bundle(entry: &ArchiveEntry, repo_dir: &Path) -> Result<()> {
+    let metadata = &entry.metadata;
+
+    // Stashes other than the latest and detached checkouts are reachable
+    // from no ref `--all` would include.
+    for (index, stash) in metadata.stashes.iter().enumerate() {
+        let name = format!("{}/stash/{}", ARCHIVE_REFS, index);
+        run_git(repo_dir, &["update-ref", &name, &stash.commit])?;
+    }
+    let detached = metadata
+        .worktrees
+        .iter()
+        .filter(|wt| wt.branch.is_none())
+        .map(|wt| wt.commit.as_str())
+        .chain(
+            metadata
+                .head_commit
+                .as_deref()
+                .filter(|_| metadata.head.is_none()),
+        );
+    for (index, commit) in detached.enumerate() {
+        let name = format!("{}/head/{}", ARCHIVE_REFS, index);
+        run_git(repo_dir, &["update-ref", &name, commit])?;
+    }
+
+    fs::create_dir_all(&entry.dir)
+        .with_context(|| format!("Failed to create {}", entry.dir.display()))?;
+
+    let bundle = entry.bundle();
+    let bundle = bundle.to_str().context("Archive path is not valid UTF-8")?;
+    run_git(repo_dir, &["bundle", "create", "--quiet", bundle, "--all"])
+        .with_context(|| format!("Failed to bundle {}", metadata.repository))?;
+    run_git(repo_dir, &["bundle", "verify", "--quiet", bundle])?;
+
+    fs::write(
+        entry.dir.join(METADATA_FILE),
+        toml::to_string_pretty(metadata)?,
+    )
+    .with_context(|| {
+        format!(
+            "Failed to write archive metadata in {}",
+            entry.dir.display()
+        )
+    })?;
+
+    Ok(())
+}
+
+fn drop_archive_refs(git_dir: &Path) {
+    let refs = git_output(
+        git_dir,
+        &["for-each-ref", "--format=%(refname)", ARCHIVE_REFS],
+    )
+    .unwrap_or_default();
+    for name in refs.lines() {
+        let _ = run_git(git_dir, &["update-ref", "-d", name]);
+    }
+}
+
+fn load_entries(archive_root: &Path) -> Result<Vec<ArchiveEntry>> {
+    let mut entries = Vec::new();
+    let mut pending = vec![archive_root.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        let metadata_file = dir.join(METADATA_FILE);
+        if metadata_file.exists() {
+            let content = fs::read_to_string(&metadata_file)
+                .with_context(|| format!("Failed to read {}", metadata_file.display()))?;
+            let metadata = toml::from_str(&content)
+                .with_context(|| format!("Failed to parse {}", metadata_file.display()))?;
+            entries.push(ArchiveEntry { dir, metadata });
+            continue;
+        }
+
+        let Ok(children) = fs::read_dir(&dir) else {
+            continue;
+        };
+        pending.extend(
+            children
+                .flatten()
+                .map(|child| child.path())
+                .filter(|path| path.is_dir()),
+        );
+    }
+
+    entries.sort_by(|a, b| a.metadata.repository.cmp(&b.metadata.repository));
+    Ok(entries)
+}
+
+pub fn list_archives(config: &Config) -> Result<()> {
+    let layout = Layout::new(config)?;
+    let entries = load_entries(&archive_root(config, &layout))?;
+
+    if entries.is_empty() {
+        println!("No archived repositories");
+        return Ok(());
+    }
+
+    let now = age::now();
+    for entry in entries {
+        let metadata = &entry.metadata;
+        println!(
+            "{}  {:>4} ago  ({} worktrees, {} stashes)",
+            metadata.repository,
+            age::format_age(now.saturating_sub(metadata.archived_at)),
+            metadata.worktrees.len(),
+            metadata.stashes.len()
+        );
+    }
+
+    Ok(())
+}
+
+/// Rebuild an archived repository at its place in the layout, with its
+/// worktrees, stashes and local config, and remove it from the archive.
+pub fn unarchive(config: &Config, repository: &str) -> Result<()> {
+    let layout = Layout::new(config)?;
+    let archive_root = archive_root(config, &layout);
+    let repository = repository.trim_matches('/');
+
+    let entry = load_entries(&archive_root)?
+        .into_iter()
+        .find(|e| e.metadata.repository == repository)
+        .ok_or_else(|| anyhow::anyhow!("Not archived: {}", repository))?;
+
+    let repo_dir = restore_entry(&layout, config.fork_placement, config.remote_name(), &entry)?;
+
+    fs::remove_dir_all(&entry.dir)
+        .with_context(|| format!("Failed to remove {}", entry.dir.display()))?;
+    relocate::remove_empty_parents(&entry.dir, &archive_root);
+
+    println!("✓ Unarchived: {}", repo_dir.display());
+    println!("{}", repository::resolve_checkout(&repo_dir)?.display());
+    Ok(())
+}
+
+/// Where an archived repository goes: the location its remote maps to in
+/// the current layout, or its old path under the root without one.
+fn target_dir(
+    layout: &Layout,
+    placement: ForkPlacement,
+    remote: &str,
+    metadata: &ArchiveMetadata,
+) -> PathBuf {
+    let url = |name: &str| {
+        metadata
+            .remotes
+            .iter()
+            .find(|remote| remote.name == name)
+            .map(|remote| remote.url.as_str())
+    };
+    let url = match placement {
+        ForkPlacement::Upstream => url(UPSTREAM_REMOTE).or_else(|| url(remote)),
+        ForkPlacement::Fork => url(remote),
+    };
+
+    url.and_then(|url| path::parse_repo_path(url).ok())
+        .and_then(|repo_path| layout.repo_dir(&repo_path).ok())
+        .unwrap_or_else(|| layout.root.join(&metadata.repository))
+}
+
+fn restore_entry(
+    layout: &Layout,
+    placement: ForkPlacement,
+    remote: &str,
+    entry: &ArchiveEntry,
+) -> Result<PathBuf> {
+    let metadata = &entry.metadata;
+    let repo_dir = target_dir(layout, placement, remote, metadata);
+
+    if repo_dir.exists() {
+        anyhow::bail!(
+            "Cannot unarchive {}: {} already exists",
+            metadata.repository,
+            repo_dir.display()
+        );
+    }
+
+    fs::create_dir_all(&repo_dir)
+        .with_context(|| format!("Failed to create {}", repo_dir.display()))?;
+
+    let git_dir = if metadata.bare {
+        run_git(&repo_dir, &["init", "--quiet", "--bare", ".git"])?;
+        repo_dir.join(".git")
+    } else {
+        run_git(&repo_dir, &["init", "--quiet"])?;
+        repo_dir.clone()
+    };
+
+    for entry in &metadata.config {
+        run_git(
+            &git_dir,
+            &["config", "--local", "--add", &entry.key, &entry.value],
+        )?;
+    }
+
+    let bundle = entry.bundle();
+    let bundle = bundle.to_str().context("Archive path is not valid UTF-8")?;
+    run_git(
+        &git_dir,
+        &[
+            "fetch",
+            "--quiet",
+            "--update-head-ok",
+            bundle,
+            "refs/*:refs/*",
+        ],
+    )?;
+
+    // The bundle only has the latest stash; the whole list is rebuilt from
+    // the metadata, oldest first.
+    let _ = run_git(&git_dir, &["update-ref", "-d", "refs/stash"]);
+    for stash in metadata.stashes.iter().rev() {
+        run_git(
+            &git_dir,
+            &[
+                "update-ref",
+                "--create-reflog",
+                "-m",
+                &stash.message,
+                "refs/stash",
+                &stash.commit,
+            ],
+        )?;
+    }
+    drop_archive_refs(&git_dir);
+
+    match (&metadata.head, &metadata.head_commit) {
+        (Some(branch), _) => {
+            let head = format!("refs/heads/{}", branch);
+            run_git(&git_dir, &["symbolic-ref", "HEAD", &head])?;
+        }
+        (None, Some(commit)) => run_git(&git_dir, &["update-ref", "--no-deref", "HEAD", commit])?,
+        (None, None) => {}
+    }
+    if !metadata.bare {
+        run_git(&repo_dir, &["reset", "--quiet", "--hard"])?;
+    }
+
+    let worktree_base = layout
+        .worktree_dir_for(&repo_dir)
+        .unwrap_or_else(|| layout.worktree_base().join(&metadata.repository));
+    for wt in &metadata.worktrees {
+        let name = match wt.branch {
+            Some(ref branch) => PathBuf::from(branch),
+            None => PathBuf::from(wt.original_path.file_name().unwrap_or_default()),
+        };
+        let path = worktree_base.join(name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+
+        let path_arg = path.to_str().context("Worktree path is not valid UTF-8")?;
+        match wt.branch {
+            Some(ref branch) => {
+                run_git(&git_dir, &["worktree", "add", "--quiet", path_arg, branch])?
+            }
+            None => run_git(
+                &git_dir,
+                &[
+                    "worktree", "add", "--quiet", "--detach", path_arg, &wt.commit,
+                ],
+            )?,
+        }
+        println!("✓ Restored worktree: {}", path.display());
+    }
+
+    Ok(repo_dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::DEFAULT_TEMPLATE;
+    use crate::test_support::{commit, init_repo, temp_dir};
+
+    #[test]
+    fn test_archive_and_restore() {
+        let tmp = temp_dir("archive");
+        let layout = Layout::with_template(tmp.join("src"), DEFAULT_TEMPLATE).unwrap();
+        let archive_root = tmp.join("archive");
+
+        let repo = tmp.join("src/example.com/org/repo");
+        init_repo(&repo, &["a.txt"]);
+        run_git(
+            &repo,
+            &["remote", "add", "origin", "https://example.com/org/repo"],
+        )
+        .unwrap();
+        run_git(&repo, &["config", "user.email", "me@example.com"]).unwrap();
+        let feature = tmp.join("elsewhere/feature");
+        run_git(
+            &repo,
+            &[
+                "worktree",
+                "add",
+                "--quiet",
+                "-b",
+                "feature",
+                feature.to_str().unwrap(),
+            ],
+        )
+        .unwrap();
+        commit(&feature, "b.txt");
+
+        for file in ["one.txt", "two.txt"] {
+            fs::write(repo.join("a.txt"), file).unwrap();
+            run_git(
+                &repo,
+                &[
+                    "-c",
+                    "user.name=test",
+                    "stash",
+                    "push",
+                    "--quiet",
+                    "-m",
+                    file,
+                ],
+            )
+            .unwrap();
+        }
+
+        let entry = write_archive(&archive_root, "example.com/org/repo", &repo).unwrap();
+        assert_eq!(entry.metadata.head.as_deref(), Some("main"));
+        assert_eq!(entry.metadata.worktrees.len(), 1);
+        assert_eq!(entry.metadata.stashes.len(), 2);
+        assert!(entry.bundle().exists());
+        let refs = git_output(&repo, &["for-each-ref", ARCHIVE_REFS]).unwrap();
+        assert!(refs.is_empty());
+
+        let entries = load_entries(&archive_root).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].metadata, entry.metadata);
+
+        fs::remove_dir_all(&repo).unwrap();
+        fs::remove_dir_all(&feature).unwrap();
+
+        let restored =
+            restore_entry(&layout, ForkPlacement::Upstream, "origin", &entries[0]).unwrap();
+        assert_eq!(restored, repo);
+        assert!(repo.join("a.txt").exists());
+        assert_eq!(
+            git_output(&repo, &["config", "--local", "user.email"]).unwrap(),
+            "me@example.com"
+        );
+        assert_eq!(
+            git_output(&repo, &["stash", "list", "--format=%gs"]).unwrap(),
+            "On main: two.txt\nOn main: one.txt"
+        );
+
+        let worktrees = Worktree::list(&repo).unwrap();
+        assert_eq!(worktrees.len(), 2);
+        let worktree = &worktrees[1].path;
+        assert!(worktree.ends_with("example.com/org/repo/feature"));
+        assert!(worktree.join("b.txt").exists());
+        assert_eq!(
+            git_output(worktree, &["branch", "--show-current"]).unwrap(),
+            "feature"
+        );
+
+        fs::remove_dir_all(&tmp).ok();
+    }
+}
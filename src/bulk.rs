@@ -0,0 +1,281 @@
+use anyhow::Result;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::config::Config;
+use crate::discovery;
+use crate::git::{ahead_behind, git_output, run_git};
+use crate::pool;
+use crate::repository;
+use crate::selection::Selection;
+
+/// A repository under the src root, named by its path relative to it.
+struct Target {
+    name: String,
+    dir: PathBuf,
+}
+
+#[derive(Debug, PartialEq)]
+enum Outcome {
+    Updated,
+    UpToDate,
+    Diverged,
+    Skipped(String),
+    Failed(String),
+}
+
+impl Outcome {
+    fn label(&self) -> &'static str {
+        match self {
+            Outcome::Updated => "updated",
+            Outcome::UpToDate => "up-to-date",
+            Outcome::Diverged => "diverged",
+            Outcome::Skipped(_) => "skipped",
+            Outcome::Failed(_) => "failed",
+        }
+    }
+
+    fn symbol(&self) -> &'static str {
+        match self {
+            Outcome::Updated | Outcome::UpToDate => "✓",
+            Outcome::Diverged | Outcome::Skipped(_) => "!",
+            Outcome::Failed(_) => "✗",
+        }
+    }
+
+    fn detail(&self) -> Option<&str> {
+        match self {
+            Outcome::Skipped(detail) | Outcome::Failed(detail) => Some(detail),
+            _ => None,
+        }
+    }
+}
+
+/// Fetch every remote of every selected repository.
+pub fn fetch_repositories(config: &Config, selection: &Selection, jobs: usize) -> Result<()> {
+    run(config, selection, jobs, "Fetching", fetch)
+}
+
+/// Fetch, then fast-forward the checked-out branch of every selected
+/// repository to its upstream. Bare hubs fast-forward the worktree of
+/// their default branch. Branches that would need a merge or rebase are
+/// reported as diverged and left alone.
+pub fn pull_repositories(config: &Config, selection: &Selection, jobs: usize) -> Result<()> {
+    run(config, selection, jobs, "Pulling", pull)
+}
+
+/// Run `work` over the selected repositories. On a terminal, a status line
+/// on stderr names the repository most recently started, so slow fetches
+/// show progress before anything finishes.
+fn run(
+    config: &Config,
+    selection: &Selection,
+    jobs: usize,
+    verb: &str,
+    work: fn(&Path) -> Outcome,
+) -> Result<()> {
+    let src_root = repository::get_src_root(config)?;
+    let targets: Vec<Target> = selection
+        .filter(config, discovery::find_repositories(config, false)?)?
+        .into_iter()
+        .map(|dir| Target {
+            name: dir
+                .strip_prefix(&src_root)
+                .unwrap_or(&dir)
+                .display()
+                .to_string(),
+            dir,
+        })
+        .collect();
+
+    if targets.is_empty() {
+        if selection.is_all() {
+            println!("No repositories found in {}", src_root.display());
+        } else {
+            println!("No repositories match {}", selection);
+        }
+        return Ok(());
+    }
+
+    // Workers run side by side; a credential prompt would stall them all.
+    std::env::set_var("GIT_TERMINAL_PROMPT", "0");
+
+    let total = targets.len();
+    let width = total.to_string().len();
+    let status_line = io::stderr().is_terminal();
+    let started = AtomicUsize::new(0);
+
+    println!(
+        "{} {} repositories ({} at a time)",
+        verb,
+        total,
+        jobs.clamp(1, total)
+    );
+    let outcomes = pool::run(
+        &targets,
+        jobs,
+        |target| {
+            let index = started.fetch_add(1, Ordering::SeqCst) + 1;
+            if status_line {
+                eprint!("\r\x1b[K{} {} ({}/{})...", verb, target.name, index, total);
+            }
+            work(&target.dir)
+        },
+        |done, target, outcome| {
+            if status_line {
+                eprint!("\r\x1b[K");
+            }
+            let detail = outcome
+                .detail()
+                .and_then(|d| d.lines().next())
+                .map(|d| format!(" ({})", d))
+                .unwrap_or_default();
+            println!(
+                "[{:>width$}/{}] {} {} {}{}",
+                done,
+                total,
+                outcome.symbol(),
+                target.name,
+                outcome.label(),
+                detail,
+                width = width
+            );
+        },
+    );
+
+    if status_line {
+        eprint!("\r\x1b[K");
+    }
+    print_summary(&targets, &outcomes);
+
+    let failed = outcomes
+        .iter()
+        .filter(|o| matches!(o, Outcome::Failed(_)))
+        .count();
+    if failed > 0 {
+        anyhow::bail!("{} of {} repositories failed", failed, total);
+    }
+
+    Ok(())
+}
+
+fn print_summary(targets: &[Target], outcomes: &[Outcome]) {
+    println!();
+    println!("{:<12} {:>5}", "STATUS", "COUNT");
+
+    for label in ["updated", "up-to-date", "diverged", "skipped", "failed"] {
+        let matching: Vec<(&Target, &Outcome)> = targets
+            .iter()
+            .zip(outcomes)
+            .filter(|(_, o)| o.label() == label)
+            .collect();
+        if matching.is_empty() {
+            continue;
+        }
+
+        println!("{:<12} {:>5}", label, matching.len());
+        if label == "up-to-date" {
+            continue;
+        }
+        for (target, outcome) in matching {
+            match outcome.detail() {
+                Some(detail) => println!("  {}: {}", target.name, detail),
+                None => println!("  {}", target.name),
+            }
+        }
+    }
+}
+
+fn fetch(dir: &Path) -> Outcome {
+    let changed = match fetch_all(dir) {
+        Ok(changed) => changed,
+        Err(e) => return Outcome::Failed(e.to_string()),
+    };
+
+    let checkout = repository::resolve_checkout(dir).unwrap_or_else(|_| dir.to_path_buf());
+    match ahead_behind(&checkout) {
+        Some((ahead, behind)) if ahead > 0 && behind > 0 => Outcome::Diverged,
+        _ if changed => Outcome::Updated,
+        _ => Outcome::UpToDate,
+    }
+}
+
+fn pull(dir: &Path) -> Outcome {
+    if let Err(e) = fetch_all(dir) {
+        return Outcome::Failed(e.to_string());
+    }
+
+    let checkout = match repository::resolve_checkout(dir) {
+        Ok(checkout) => checkout,
+        Err(e) => return Outcome::Failed(e.to_string()),
+    };
+
+    match ahead_behind(&checkout) {
+        None => Outcome::Skipped("no upstream branch".to_string()),
+        Some((_, 0)) => Outcome::UpToDate,
+        Some((ahead, _)) if ahead > 0 => Outcome::Diverged,
+        Some(_) => match run_git(&checkout, &["merge", "--ff-only", "--quiet", "@{upstream}"]) {
+            Ok(()) => Outcome::Updated,
+            Err(e) => Outcome::Failed(e.to_string()),
+        },
+    }
+}
+
+/// Fetch all remotes and report whether any remote-tracking ref or tag
+/// moved.
+fn fetch_all(dir: &Path) -> Result<bool> {
+    let refs = [
+        "for-each-ref",
+        "--format=%(refname) %(objectname)",
+        "refs/remotes",
+        "refs/tags",
+    ];
+
+    let before = git_output(dir, &refs)?;
+    run_git(dir, &["fetch", "--all", "--prune", "--quiet"])?;
+    let after = git_output(dir, &refs)?;
+
+    Ok(before != after)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{commit, init_repo, temp_dir};
+
+    #[test]
+    fn test_fetch_and_pull_outcomes() {
+        let tmp = temp_dir("bulk");
+        let remote = tmp.join("remote");
+        init_repo(&remote, &["a.txt"]);
+
+        let clone = |name: &str| {
+            let dir = tmp.join(name);
+            run_git(&tmp, &["clone", "--quiet", remote.to_str().unwrap(), name]).unwrap();
+            dir
+        };
+        let behind = clone("behind");
+        let diverged = clone("diverged");
+        let current = clone("current");
+        run_git(&current, &["checkout", "--quiet", "-b", "local"]).unwrap();
+
+        commit(&remote, "b.txt");
+        commit(&diverged, "local.txt");
+
+        assert_eq!(fetch(&behind), Outcome::Updated);
+        assert_eq!(fetch(&behind), Outcome::UpToDate);
+        assert_eq!(fetch(&diverged), Outcome::Diverged);
+
+        assert_eq!(pull(&behind), Outcome::Updated);
+        assert_eq!(pull(&behind), Outcome::UpToDate);
+        assert!(behind.join("b.txt").exists());
+        assert_eq!(pull(&diverged), Outcome::Diverged);
+        assert!(matches!(pull(&current), Outcome::Skipped(_)));
+
+        run_git(&behind, &["remote", "set-url", "origin", "/nonexistent"]).unwrap();
+        assert!(matches!(pull(&behind), Outcome::Failed(_)));
+
+        std::fs::remove_dir_all(&tmp).ok();
+    }
+}
@@ -0,0 +1,263 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::config::Config;
+use crate::git::{git_output, run_git};
+use crate::layout::Layout;
+use crate::path;
+use crate::shorthand;
+
+/// Objects a repository can reach, split by where they are stored.
+pub struct ObjectStats {
+    pub total: u64,
+    pub local: u64,
+}
+
+impl ObjectStats {
+    pub fn borrowed(&self) -> u64 {
+        self.total.saturating_sub(self.local)
+    }
+}
+
+/// Make sure the cache holds an up-to-date copy of `url`. A new cache
+/// fetches with `ssh_command`, the key of the repository's identity, and
+/// keeps it for later refreshes.
+pub fn ensure(cache_repo: &Path, url: &str, ssh_command: Option<&str>) -> Result<()> {
+    if cache_repo.exists() {
+        refresh(cache_repo)
+    } else {
+        add(cache_repo, url, ssh_command)
+    }
+}
+
+fn add(cache_repo: &Path, url: &str, ssh_command: Option<&str>) -> Result<()> {
+    if let Some(parent) = cache_repo.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create cache directory: {}", parent.display()))?;
+    }
+
+    println!("Caching {} in {}...", url, cache_repo.display());
+
+    let mut cmd = Command::new("git");
+    cmd.args(["clone", "--bare", "--quiet"]);
+    if let Some(ssh_command) = ssh_command {
+        cmd.arg(format!("--config=core.sshCommand={}", ssh_command));
+    }
+    let status = cmd
+        .arg(url)
+        .arg(cache_repo)
+        .status()
+        .context("Failed to execute git clone --bare")?;
+
+    if !status.success() {
+        fs::remove_dir_all(cache_repo).ok();
+        anyhow::bail!("Failed to populate cache for {}", url);
+    }
+
+    run_git(
+        cache_repo,
+        &[
+            "config",
+            "remote.origin.fetch",
+            "+refs/heads/*:refs/heads/*",
+        ],
+    )?;
+
+    Ok(())
+}
+
+/// Deleted branches are deliberately not pruned: clones borrowing from the
+/// cache may still need their objects.
+fn refresh(cache_repo: &Path) -> Result<()> {
+    run_git(cache_repo, &["fetch", "--quiet", "--tags", "origin"])
+}
+
+pub fn object_stats(repo_dir: &Path) -> Result<ObjectStats> {
+    let objects = git_output(
+        repo_dir,
+        &["rev-list", "--all", "--objects", "--missing=allow-any"],
+    )?;
+    let total = objects.lines().count() as u64;
+
+    let counts = git_output(repo_dir, &["count-objects", "-v"])?;
+    let local = counts
+        .lines()
+        .filter_map(|line| line.split_once(": "))
+        .filter(|(key, _)| *key == "count" || *key == "in-pack")
+        .filter_map(|(_, value)| value.parse::<u64>().ok())
+        .sum();
+
+    Ok(ObjectStats { total, local })
+}
+
+pub fn add_repositories(config: &Config, inputs: &[String]) -> Result<()> {
+    let layout = Layout::new(config)?;
+
+    for input in inputs {
+        let url = shorthand::resolve_clone_url(input, config)?;
+        let repo_path = path::parse_repo_path(&url)?;
+        let ssh_command = config
+            .identity_for(&repo_path)
+            .and_then(|identity| identity.ssh_command.as_deref());
+        ensure(&layout.cache_repo_dir(&repo_path), &url, ssh_command)?;
+        println!("✓ Cached: {}", repo_path);
+    }
+
+    Ok(())
+}
+
+pub fn refresh_repositories(config: &Config) -> Result<()> {
+    let layout = Layout::new(config)?;
+
+    for cache_repo in find_cached(&layout.cache_dir()) {
+        let name = cache_name(&layout, &cache_repo);
+        match refresh(&cache_repo) {
+            Ok(()) => println!("✓ Refreshed: {}", name),
+            Err(e) => println!("✗ {}: {}", name, e),
+        }
+    }
+
+    Ok(())
+}
+
+/// Repack every cached repository. Unreachable objects are kept since other
+/// clones may borrow them through alternates.
+pub fn gc_repositories(config: &Config) -> Result<()> {
+    let layout = Layout::new(config)?;
+
+    for cache_repo in find_cached(&layout.cache_dir()) {
+        run_git(&cache_repo, &["gc", "--quiet", "--prune=never"])?;
+        println!("✓ Packed: {}", cache_name(&layout, &cache_repo));
+    }
+
+    Ok(())
+}
+
+/// Copy borrowed objects into a repository so it no longer depends on the
+/// cache.
+pub fn dissociate(config: &Config, repository: Option<&str>) -> Result<()> {
+    let layout = Layout::new(config)?;
+
+    let repo_dir = match repository {
+        Some(repo) => layout.root.join(repo),
+        None => std::env::current_dir().context("Failed to get current directory")?,
+    };
+
+    let git_dir = PathBuf::from(git_output(
+        &repo_dir,
+        &["rev-parse", "--path-format=absolute", "--git-common-dir"],
+    )?);
+    let alternates = git_dir.join("objects/info/alternates");
+
+    if !alternates.exists() {
+        println!("Not using the object cache: {}", repo_dir.display());
+        return Ok(());
+    }
+
+    run_git(&repo_dir, &["repack", "-a", "-d", "--quiet"])?;
+    fs::remove_file(&alternates)
+        .with_context(|| format!("Failed to remove {}", alternates.display()))?;
+
+    println!("✓ Dissociated from cache: {}", repo_dir.display());
+    Ok(())
+}
+
+pub fn list_repositories(config: &Config) -> Result<()> {
+    let layout = Layout::new(config)?;
+    let cached = find_cached(&layout.cache_dir());
+
+    if cached.is_empty() {
+        println!("Object cache is empty: {}", layout.cache_dir().display());
+        return Ok(());
+    }
+
+    for cache_repo in cached {
+        println!("{}", cache_name(&layout, &cache_repo));
+    }
+
+    Ok(())
+}
+
+fn cache_name(layout: &Layout, cache_repo: &Path) -> String {
+    let relative = cache_repo
+        .strip_prefix(layout.cache_dir())
+        .unwrap_or(cache_repo)
+        .display()
+        .to_string();
+    relative
+        .strip_suffix(".git")
+        .unwrap_or(&relative)
+        .to_string()
+}
+
+fn find_cached(dir: &Path) -> Vec<PathBuf> {
+    let mut repos = Vec::new();
+    collect_cached(dir, &mut repos);
+    repos.sort();
+    repos
+}
+
+fn collect_cached(dir: &Path, repos: &mut Vec<PathBuf>) {
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if !path.is_dir() {
+                continue;
+            }
+
+            if path.extension().is_some_and(|ext| ext == "git") {
+                repos.push(path);
+            } else {
+                collect_cached(&path, repos);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{CloneLayout, CloneOptions};
+    use crate::git;
+    use crate::layout::DEFAULT_TEMPLATE;
+    use crate::test_support::{commit, init_repo, temp_dir};
+
+    #[test]
+    fn test_clone_borrows_from_cache() {
+        let tmp = temp_dir("cache");
+        let remote = tmp.join("remote");
+        init_repo(&remote, &["a.txt", "b.txt"]);
+        let url = format!("file://{}", remote.display());
+
+        let layout = Layout::with_template(tmp.join("src"), DEFAULT_TEMPLATE).unwrap();
+        let repo_path = path::parse_repo_path(&url).unwrap();
+        let cache_repo = layout.cache_repo_dir(&repo_path);
+
+        ensure(&cache_repo, &url, None).unwrap();
+        assert!(cache_repo.join("HEAD").exists());
+        assert_eq!(find_cached(&layout.cache_dir()), vec![cache_repo.clone()]);
+
+        let options = CloneOptions {
+            cache: Some(true),
+            ..Default::default()
+        };
+        git::clone_repository(&layout, &url, CloneLayout::Standard, &options, None).unwrap();
+
+        let clone = layout.repo_dir(&repo_path).unwrap();
+        assert!(clone.join(".git/objects/info/alternates").exists());
+
+        let stats = object_stats(&clone).unwrap();
+        assert_eq!(stats.local, 0);
+        assert!(stats.borrowed() >= 6);
+
+        // A refresh picks up new commits without touching the clone.
+        commit(&remote, "c.txt");
+        ensure(&cache_repo, &url, None).unwrap();
+        let log = git_output(&cache_repo, &["log", "--oneline", "main"]).unwrap();
+        assert_eq!(log.lines().count(), 3);
+
+        fs::remove_dir_all(&tmp).ok();
+    }
+}
@@ -1,4 +1,6 @@
-use clap::{Parser, Subcommand};
+use clap::{ArgGroup, Parser, Subcommand};
+
+use crate::history::SortOrder;
 
 #[derive(Parser)]
 #[command(name = "g")]
@@ -40,43 +42,402 @@ pub enum Commands {
         cmd: BranchCommands,
     },
 
+    #[command(about = "Run a command in many repositories or worktrees in parallel")]
+    #[command(group(
+        ArgGroup::new("targets")
+            .required(true)
+            .multiple(true)
+            .args(["group", "all_repos", "worktrees"])
+    ))]
+    SonicForeach {
+        #[arg(
+            short,
+            long,
+            help = "Repositories of this group (sonic-git.group.<name>)"
+        )]
+        group: Vec<String>,
+
+        #[arg(
+            long,
+            conflicts_with = "group",
+            help = "Every repository under the src root"
+        )]
+        all_repos: bool,
+
+        #[arg(
+            long,
+            help = "Run in worktrees: of the current repository, or of every selected one"
+        )]
+        worktrees: bool,
+
+        #[arg(
+            short,
+            long,
+            help = "Number of parallel jobs (default: number of CPUs)"
+        )]
+        jobs: Option<usize>,
+
+        #[arg(long, help = "Start no further commands once one fails")]
+        fail_fast: bool,
+
+        #[arg(
+            long,
+            conflicts_with = "fail_fast",
+            help = "Run every command whatever the others do (default)"
+        )]
+        keep_going: bool,
+
+        #[arg(
+            last = true,
+            required = true,
+            help = "Command to run; a single argument is run with sh -c"
+        )]
+        command: Vec<String>,
+    },
+
+    #[command(about = "Inspect or prune the usage history behind --sort=frecent")]
+    SonicHistory {
+        #[command(subcommand)]
+        cmd: HistoryCommands,
+    },
+
     #[command(external_subcommand)]
     External(Vec<String>),
 }
 
 #[derive(Subcommand)]
 pub enum RepositoryCommands {
-    #[command(about = "Clone a repository to $HOME/src/{host}/{org}/{repo}")]
+    #[command(about = "Clone a repository to $HOME/src/{host}/{owner}/{repo}")]
     Clone {
-        #[arg(help = "Git repository URL")]
+        #[arg(help = "Git repository URL or shorthand (org/repo, gh:org/repo)")]
         url: String,
+
+        #[arg(
+            long,
+            help = "Clone into a bare .git hub and check out the default branch as a worktree"
+        )]
+        bare: bool,
+
+        #[arg(long, help = "Partial clone filter (e.g., blob:none)")]
+        filter: Option<String>,
+
+        #[arg(long, help = "Create a shallow clone with this many commits")]
+        depth: Option<u32>,
+
+        #[arg(short, long, help = "Branch to check out instead of the remote HEAD")]
+        branch: Option<String>,
+
+        #[arg(long, help = "Clone only the history of one branch")]
+        single_branch: bool,
+
+        #[arg(
+            long,
+            conflicts_with = "single_branch",
+            help = "Clone all branches, even if singleBranch is configured"
+        )]
+        no_single_branch: bool,
+
+        #[arg(long, help = "Initialize submodules recursively")]
+        recurse_submodules: bool,
+
+        #[arg(
+            long,
+            conflicts_with = "recurse_submodules",
+            help = "Do not initialize submodules, even if recurseSubmodules is configured"
+        )]
+        no_recurse_submodules: bool,
+
+        #[arg(long, help = "Borrow objects from the shared object cache")]
+        cache: bool,
+
+        #[arg(long, conflicts_with = "cache", help = "Do not use the object cache")]
+        no_cache: bool,
+
+        #[arg(
+            long,
+            visible_alias = "fork-of",
+            help = "Repository the URL is a fork of, added as the 'upstream' remote"
+        )]
+        upstream: Option<String>,
+
+        #[arg(long, help = "Do not run post-clone hooks")]
+        no_hooks: bool,
     },
 
     #[command(about = "List all repositories")]
-    Ls,
+    Ls {
+        #[arg(
+            help = "Only repositories matching these patterns or @groups (e.g., github.com/acme)"
+        )]
+        filters: Vec<String>,
+
+        #[arg(long, help = "Rescan the src root instead of using the index")]
+        refresh: bool,
+
+        #[arg(long, help = "Group the list by host and owner")]
+        tree: bool,
+    },
 
     #[command(about = "Switch to a repository")]
     Switch {
-        #[arg(help = "Repository name (e.g., github.com/user/repo)")]
+        #[arg(
+            help = "Repository name (e.g., github.com/user/repo); with -i, a pattern or @group to choose from"
+        )]
         repository: Option<String>,
 
         #[arg(short, long, help = "Interactive selection with fzf")]
         interactive: bool,
+
+        #[arg(long, help = "Rescan the src root instead of using the index")]
+        refresh: bool,
+
+        #[arg(long, value_enum, default_value_t = SortOrder::Frecent, help = "Order of the interactive list")]
+        sort: SortOrder,
     },
 
-    #[command(about = "Delete a repository")]
+    #[command(about = "Move a repository and its worktrees to the trash")]
     Delete {
-        #[arg(help = "Repository name (e.g., github.com/user/repo)")]
+        #[arg(
+            help = "Repository name (e.g., github.com/user/repo); with -i, a pattern or @group to choose from"
+        )]
         repository: Option<String>,
 
         #[arg(short, long, help = "Interactive selection with fzf")]
         interactive: bool,
+
+        #[arg(long, help = "Rescan the src root instead of using the index")]
+        refresh: bool,
+
+        #[arg(short, long, help = "Do not ask for confirmation")]
+        yes: bool,
+
+        #[arg(
+            short,
+            long,
+            help = "Delete even with uncommitted, stashed or unpushed work"
+        )]
+        force: bool,
     },
 
     #[command(about = "Create a new repository")]
     New {
         #[arg(help = "Repository path (e.g., github.com/user/repo)")]
         repository: String,
+
+        #[arg(
+            long,
+            help = "Copy this template (sonic-git.template.<name> or the templates dir)"
+        )]
+        template: Option<String>,
+
+        #[arg(short = 'b', long, help = "Name of the initial branch")]
+        initial_branch: Option<String>,
+
+        #[arg(long, help = "Commit the initial contents")]
+        commit: bool,
+
+        #[arg(
+            long,
+            help = "Add an origin remote for the repository path, using the host's protocol"
+        )]
+        origin: bool,
+    },
+
+    #[command(about = "Move existing clones into the configured layout template")]
+    Migrate {
+        #[arg(
+            long,
+            help = "Layout template to migrate to (default: sonic-git.layoutTemplate)"
+        )]
+        template: Option<String>,
+
+        #[arg(long, help = "Print planned moves without moving anything")]
+        dry_run: bool,
+    },
+
+    #[command(about = "Move a repository and its worktrees, by default to where its origin maps")]
+    Mv {
+        #[arg(help = "Repository path relative to the src root (default: current repository)")]
+        repository: Option<String>,
+
+        #[arg(help = "New repository path (e.g., github.com/new-org/repo; default: from origin)")]
+        destination: Option<String>,
+
+        #[arg(
+            long,
+            conflicts_with_all = ["repository", "destination"],
+            help = "List repositories whose location does not match their origin"
+        )]
+        check: bool,
+    },
+
+    #[command(about = "Move existing clones into the src root by their origin")]
+    Adopt {
+        #[arg(help = "Paths of clones to adopt")]
+        paths: Vec<String>,
+
+        #[arg(long, help = "Also adopt every repository found under this directory")]
+        scan: Option<String>,
+
+        #[arg(long, help = "Print planned moves without moving anything")]
+        dry_run: bool,
+    },
+
+    #[command(about = "Bundle a repository into the archive and remove its checkout")]
+    Archive {
+        #[arg(
+            required_unless_present = "list",
+            help = "Repository path relative to the src root"
+        )]
+        repository: Option<String>,
+
+        #[arg(
+            long,
+            conflicts_with = "repository",
+            help = "List archived repositories"
+        )]
+        list: bool,
+
+        #[arg(long, help = "Archive even with uncommitted changes, which are lost")]
+        force: bool,
+    },
+
+    #[command(about = "Rebuild an archived repository and its worktrees")]
+    Unarchive {
+        #[arg(help = "Repository path as shown by archive --list")]
+        repository: String,
+    },
+
+    #[command(about = "Clone missing and fetch existing repositories from a manifest")]
+    Sync {
+        #[arg(long, default_value = "repos.toml", help = "Manifest file")]
+        manifest: String,
+
+        #[arg(long, help = "Only sync repositories in this group")]
+        group: Option<String>,
+
+        #[arg(long, help = "Do not run post-clone hooks")]
+        no_hooks: bool,
+    },
+
+    #[command(about = "Write all repositories as a manifest")]
+    Export {
+        #[arg(short, long, help = "Output file (default: stdout)")]
+        output: Option<String>,
+    },
+
+    #[command(about = "Show branch, changes and sync state of every repository")]
+    Status {
+        #[arg(help = "Only repositories matching these patterns or @groups")]
+        filters: Vec<String>,
+
+        #[arg(long, help = "Only repositories with uncommitted changes")]
+        dirty: bool,
+
+        #[arg(long, help = "Only repositories with commits not pushed upstream")]
+        unpushed: bool,
+    },
+
+    #[command(about = "Fetch all repositories in parallel")]
+    Fetch {
+        #[arg(
+            help = "Only repositories matching these patterns or @groups (e.g., github.com/poi2)"
+        )]
+        filters: Vec<String>,
+
+        #[arg(
+            short,
+            long,
+            help = "Number of parallel jobs (default: number of CPUs)"
+        )]
+        jobs: Option<usize>,
+    },
+
+    #[command(about = "Fast-forward all repositories in parallel")]
+    Pull {
+        #[arg(
+            help = "Only repositories matching these patterns or @groups (e.g., github.com/poi2)"
+        )]
+        filters: Vec<String>,
+
+        #[arg(
+            short,
+            long,
+            help = "Number of parallel jobs (default: number of CPUs)"
+        )]
+        jobs: Option<usize>,
+    },
+
+    #[command(about = "Manage the shared object cache")]
+    Cache {
+        #[command(subcommand)]
+        cmd: CacheCommands,
+    },
+
+    #[command(about = "Check repositories against identity profiles")]
+    Identity {
+        #[command(subcommand)]
+        cmd: IdentityCommands,
+    },
+
+    #[command(about = "List, restore or empty deleted repositories")]
+    Trash {
+        #[command(subcommand)]
+        cmd: TrashCommands,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum CacheCommands {
+    #[command(about = "Add repositories to the object cache")]
+    Add {
+        #[arg(required = true, help = "Git repository URLs or shorthands")]
+        urls: Vec<String>,
+    },
+
+    #[command(about = "Fetch the latest objects into every cached repository")]
+    Refresh,
+
+    #[command(about = "Repack cached repositories")]
+    Gc,
+
+    #[command(about = "Copy cached objects into a clone so it no longer uses the cache")]
+    Dissociate {
+        #[arg(help = "Repository path relative to the src root (default: current directory)")]
+        repository: Option<String>,
+    },
+
+    #[command(about = "List cached repositories")]
+    Ls,
+}
+
+#[derive(Subcommand)]
+pub enum IdentityCommands {
+    #[command(about = "Report repositories whose identity differs from their profile")]
+    Check {
+        #[arg(
+            long,
+            help = "Write the matching profile into each mismatched repository"
+        )]
+        fix: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum TrashCommands {
+    #[command(about = "List deleted repositories, newest first")]
+    Ls,
+
+    #[command(about = "Move a deleted repository and its worktrees back")]
+    Restore {
+        #[arg(help = "Trash ID, or repository name for its latest deletion")]
+        id: String,
+    },
+
+    #[command(about = "Permanently delete repositories in the trash")]
+    Empty {
+        #[arg(long, help = "Only those deleted longer ago than this (e.g., 30d)")]
+        older_than: Option<String>,
     },
 }
 
@@ -110,6 +471,9 @@ pub enum WorktreeCommands {
 
         #[arg(short, long, help = "Interactive selection with fzf")]
         interactive: bool,
+
+        #[arg(long, value_enum, default_value_t = SortOrder::Frecent, help = "Order of the interactive list")]
+        sort: SortOrder,
     },
 
     #[command(about = "Delete worktrees")]
@@ -126,6 +490,24 @@ pub enum WorktreeCommands {
         #[arg(short, long, help = "Interactive selection with fzf")]
         interactive: bool,
     },
+
+    #[command(about = "Find worktree directories whose repository no longer knows them")]
+    Gc {
+        #[arg(
+            long,
+            help = "Scan the whole worktree base instead of the current repository's worktrees"
+        )]
+        all_repos: bool,
+
+        #[arg(
+            long,
+            help = "Delete the orphaned worktrees (default: only report them)"
+        )]
+        delete: bool,
+
+        #[arg(short, long, requires = "delete", help = "Do not ask for confirmation")]
+        yes: bool,
+    },
 }
 
 #[derive(Subcommand)]
@@ -166,3 +548,18 @@ pub enum BranchCommands {
         interactive: bool,
     },
 }
+
+#[derive(Subcommand)]
+pub enum HistoryCommands {
+    #[command(about = "List recorded repositories and worktrees by frecency")]
+    Ls,
+
+    #[command(about = "Remove entries whose path no longer exists")]
+    Prune {
+        #[arg(
+            long,
+            help = "Also remove entries not visited within this age (e.g., 90d)"
+        )]
+        older_than: Option<String>,
+    },
+}
@@ -1,11 +1,238 @@
 use anyhow::{Context, Result};
 use git2::Config as GitConfig;
 use std::collections::HashMap;
+use std::path::PathBuf;
 
+use crate::discovery;
+use crate::history;
+use crate::pattern;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CloneLayout {
+    #[default]
+    Standard,
+    Bare,
+}
+
+impl CloneLayout {
+    fn parse(value: &str) -> Result<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "standard" => Ok(CloneLayout::Standard),
+            "bare" => Ok(CloneLayout::Bare),
+            other => anyhow::bail!(
+                "Invalid sonic-git.layout: {} (expected 'standard' or 'bare')",
+                other
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Protocol {
+    #[default]
+    Https,
+    Ssh,
+}
+
+impl Protocol {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "https" => Ok(Protocol::Https),
+            "ssh" => Ok(Protocol::Ssh),
+            other => anyhow::bail!("Invalid protocol: {} (expected 'https' or 'ssh')", other),
+        }
+    }
+}
+
+/// Git identity for repositories matching one of `patterns`, configured as
+/// `sonic-git.identity.<profile>.<key>`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Identity {
+    pub profile: String,
+    pub patterns: Vec<String>,
+    pub name: Option<String>,
+    pub email: Option<String>,
+    pub signing_key: Option<String>,
+    pub ssh_command: Option<String>,
+}
+
+impl Identity {
+    fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        match key.to_ascii_lowercase().as_str() {
+            "match" => self.patterns.push(value.to_string()),
+            "name" => self.name = Some(value.to_string()),
+            "email" => self.email = Some(value.to_string()),
+            "signingkey" => self.signing_key = Some(value.to_string()),
+            "sshcommand" => self.ssh_command = Some(value.to_string()),
+            other => anyhow::bail!("Unknown identity setting: {}", other),
+        }
+        Ok(())
+    }
+
+    /// The repository-local git config this identity consists of.
+    pub fn git_config(&self) -> Vec<(&'static str, &str)> {
+        [
+            ("user.name", &self.name),
+            ("user.email", &self.email),
+            ("user.signingKey", &self.signing_key),
+            ("core.sshCommand", &self.ssh_command),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| value.as_deref().map(|value| (key, value)))
+        .collect()
+    }
+}
+
+/// A command run after cloning repositories matching `pattern`, or every
+/// repository without one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PostCloneHook {
+    pub pattern: Option<String>,
+    pub command: String,
+}
+
+/// Where a fork cloned with `--upstream` is placed under the src root.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ForkPlacement {
+    /// At the upstream repository's path, so forks sit where the project
+    /// itself would.
+    #[default]
+    Upstream,
+    /// At the fork's own path.
+    Fork,
+}
+
+impl ForkPlacement {
+    fn parse(value: &str) -> Result<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "upstream" => Ok(ForkPlacement::Upstream),
+            "fork" => Ok(ForkPlacement::Fork),
+            other => anyhow::bail!(
+                "Invalid sonic-git.forkPlacement: {} (expected 'upstream' or 'fork')",
+                other
+            ),
+        }
+    }
+}
+
+/// Knobs passed through to `git clone`.
+///
+/// Every field is optional so per-invocation flags, per-host defaults
+/// (`sonic-git.clone.<host>.<key>`) and global defaults (`sonic-git.clone.<key>`)
+/// can be layered with [`CloneOptions::or`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CloneOptions {
+    pub filter: Option<String>,
+    pub depth: Option<u32>,
+    pub branch: Option<String>,
+    pub single_branch: Option<bool>,
+    pub recurse_submodules: Option<bool>,
+    pub cache: Option<bool>,
+    /// `core.sshCommand` of the identity profile matching the repository,
+    /// so the clone itself already uses the right key. Not a config key.
+    pub ssh_command: Option<String>,
+}
+
+impl CloneOptions {
+    /// Fill every unset field from `fallback`.
+    pub fn or(self, fallback: &CloneOptions) -> CloneOptions {
+        CloneOptions {
+            filter: self.filter.or_else(|| fallback.filter.clone()),
+            depth: self.depth.or(fallback.depth),
+            branch: self.branch.or_else(|| fallback.branch.clone()),
+            single_branch: self.single_branch.or(fallback.single_branch),
+            recurse_submodules: self.recurse_submodules.or(fallback.recurse_submodules),
+            cache: self.cache.or(fallback.cache),
+            ssh_command: self.ssh_command.or_else(|| fallback.ssh_command.clone()),
+        }
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        match key.to_ascii_lowercase().as_str() {
+            "filter" => self.filter = Some(value.to_string()),
+            "depth" => {
+                self.depth = Some(
+                    value
+                        .parse()
+                        .with_context(|| format!("Invalid clone depth: {}", value))?,
+                )
+            }
+            "branch" => self.branch = Some(value.to_string()),
+            "singlebranch" => self.single_branch = Some(parse_bool(value)?),
+            "recursesubmodules" => self.recurse_submodules = Some(parse_bool(value)?),
+            "cache" => self.cache = Some(parse_bool(value)?),
+            other => anyhow::bail!("Unknown clone option: {}", other),
+        }
+        Ok(())
+    }
+
+    /// Flags for `git clone`. The object cache is wired up separately since
+    /// it needs the cache location.
+    pub fn args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+
+        if let Some(ref filter) = self.filter {
+            args.push(format!("--filter={}", filter));
+        }
+
+        if let Some(depth) = self.depth {
+            args.push(format!("--depth={}", depth));
+        }
+
+        if let Some(ref branch) = self.branch {
+            args.push(format!("--branch={}", branch));
+        }
+
+        // `--depth` implies `--single-branch`, so turning it off is explicit.
+        match self.single_branch {
+            Some(true) => args.push("--single-branch".to_string()),
+            Some(false) => args.push("--no-single-branch".to_string()),
+            None => {}
+        }
+
+        if self.recurse_submodules == Some(true) {
+            args.push("--recurse-submodules".to_string());
+        }
+
+        // Written to the new repository's config before anything is fetched.
+        if let Some(ref ssh_command) = self.ssh_command {
+            args.push(format!("--config=core.sshCommand={}", ssh_command));
+        }
+
+        args
+    }
+}
+
+#[derive(Default)]
 pub struct Config {
     #[allow(dead_code)]
     pub root: Option<String>,
+    pub layout: CloneLayout,
+    pub layout_template: Option<String>,
     pub aliases: HashMap<String, String>,
+    pub default_host: Option<String>,
+    pub host_aliases: HashMap<String, String>,
+    pub protocol: Option<Protocol>,
+    pub host_protocols: HashMap<String, Protocol>,
+    pub clone: CloneOptions,
+    pub host_clone: HashMap<String, CloneOptions>,
+    pub discovery_max_depth: Option<usize>,
+    pub discovery_ignore: Vec<String>,
+    pub remote: Option<String>,
+    pub fork_placement: ForkPlacement,
+    pub identities: Vec<Identity>,
+    pub groups: HashMap<String, Vec<String>>,
+    pub archive_dir: Option<String>,
+    pub templates: HashMap<String, String>,
+    pub templates_dir: Option<String>,
+    pub post_clone_hooks: Vec<PostCloneHook>,
+    /// Patterns of repositories whose `.sonic-git/post-clone` runs without
+    /// asking.
+    pub trusted_hooks: Vec<String>,
+    /// Where the repository index is kept; `None` walks every time.
+    pub index_path: Option<PathBuf>,
+    /// Where switches are recorded; `None` keeps no history.
+    pub history_path: Option<PathBuf>,
 }
 
 impl Config {
@@ -17,21 +244,243 @@ impl Config {
             .ok()
             .filter(|s| !s.is_empty());
 
+        let layout = git_config
+            .get_string("sonic-git.layout")
+            .ok()
+            .filter(|s| !s.is_empty())
+            .and_then(|value| parse_or_warn(CloneLayout::parse(&value)))
+            .unwrap_or_default();
+
+        let layout_template = git_config
+            .get_string("sonic-git.layoutTemplate")
+            .ok()
+            .filter(|s| !s.is_empty());
+
         let mut aliases = HashMap::new();
+        for (name, value) in entries_with_prefix(&git_config, "sonic-git.alias.") {
+            aliases.insert(name, value);
+        }
 
-        if let Ok(mut entries) = git_config.entries(Some("sonic-git.alias.*")) {
-            while let Some(entry) = entries.next() {
-                if let Ok(entry) = entry {
-                    if let (Some(name), Some(value)) = (entry.name(), entry.value()) {
-                        if let Some(alias_name) = name.strip_prefix("sonic-git.alias.") {
-                            aliases.insert(alias_name.to_string(), value.to_string());
-                        }
+        let default_host = git_config
+            .get_string("sonic-git.defaultHost")
+            .ok()
+            .filter(|s| !s.is_empty());
+
+        let protocol = git_config
+            .get_string("sonic-git.protocol")
+            .ok()
+            .filter(|s| !s.is_empty())
+            .and_then(|value| parse_or_warn(Protocol::parse(&value)));
+
+        // `sonic-git.host.<alias>` names a host; `sonic-git.host.<host>.<key>`
+        // holds settings for that host.
+        let mut host_aliases = HashMap::new();
+        let mut host_protocols = HashMap::new();
+        for (name, value) in entries_with_prefix(&git_config, "sonic-git.host.") {
+            match name.rsplit_once('.') {
+                None => {
+                    host_aliases.insert(name, value);
+                }
+                Some((host, "protocol")) => {
+                    if let Some(protocol) = parse_or_warn(Protocol::parse(&value)) {
+                        host_protocols.insert(host.to_ascii_lowercase(), protocol);
                     }
                 }
+                Some(_) => {}
             }
         }
 
-        Ok(Config { root, aliases })
+        // `sonic-git.clone.<key>` applies everywhere;
+        // `sonic-git.clone.<host>.<key>` only to clones from that host.
+        let mut clone = CloneOptions::default();
+        let mut host_clone: HashMap<String, CloneOptions> = HashMap::new();
+        for (name, value) in entries_with_prefix(&git_config, "sonic-git.clone.") {
+            let result = match name.rsplit_once('.') {
+                None => clone.set(&name, &value),
+                Some((host, key)) => host_clone
+                    .entry(host.to_ascii_lowercase())
+                    .or_default()
+                    .set(key, &value),
+            };
+            parse_or_warn(result);
+        }
+
+        let remote = git_config
+            .get_string("sonic-git.remote")
+            .ok()
+            .filter(|s| !s.is_empty());
+
+        let fork_placement = git_config
+            .get_string("sonic-git.forkPlacement")
+            .ok()
+            .filter(|s| !s.is_empty())
+            .and_then(|value| parse_or_warn(ForkPlacement::parse(&value)))
+            .unwrap_or_default();
+
+        // `sonic-git.identity.<profile>.<key>`, e.g. `identity.work.email`.
+        let mut identities: Vec<Identity> = Vec::new();
+        for (name, value) in entries_with_prefix(&git_config, "sonic-git.identity.") {
+            let Some((profile, key)) = name.rsplit_once('.') else {
+                continue;
+            };
+            let index = match identities.iter().position(|i| i.profile == profile) {
+                Some(index) => index,
+                None => {
+                    identities.push(Identity {
+                        profile: profile.to_string(),
+                        ..Default::default()
+                    });
+                    identities.len() - 1
+                }
+            };
+            parse_or_warn(identities[index].set(key, &value));
+        }
+
+        let archive_dir = git_config
+            .get_string("sonic-git.archiveDir")
+            .ok()
+            .filter(|s| !s.is_empty());
+
+        // `sonic-git.template.<name>` is the directory of a `new --template`.
+        let mut templates = HashMap::new();
+        for (name, value) in entries_with_prefix(&git_config, "sonic-git.template.") {
+            templates.insert(name, value);
+        }
+
+        let templates_dir = git_config
+            .get_string("sonic-git.templatesDir")
+            .ok()
+            .filter(|s| !s.is_empty());
+
+        // `sonic-git.hook.postClone` runs after every clone,
+        // `sonic-git.hook.<pattern>.postClone` after clones matching it.
+        let mut post_clone_hooks = Vec::new();
+        let mut trusted_hooks = Vec::new();
+        for (name, value) in entries_with_prefix(&git_config, "sonic-git.hook.") {
+            let (pattern, key) = match name.rsplit_once('.') {
+                Some((pattern, key)) => (Some(pattern.to_string()), key),
+                None => (None, name.as_str()),
+            };
+            match (pattern, key) {
+                (pattern, "postclone") => post_clone_hooks.push(PostCloneHook {
+                    pattern,
+                    command: value,
+                }),
+                (None, "trust") => trusted_hooks.push(value),
+                _ => {}
+            }
+        }
+
+        // `sonic-git.group.<name>` holds whitespace-separated patterns and may
+        // be repeated.
+        let mut groups: HashMap<String, Vec<String>> = HashMap::new();
+        for (name, value) in entries_with_prefix(&git_config, "sonic-git.group.") {
+            groups
+                .entry(name)
+                .or_default()
+                .extend(value.split_whitespace().map(str::to_string));
+        }
+
+        let mut discovery_max_depth = None;
+        let mut discovery_ignore = Vec::new();
+        for (name, value) in entries_with_prefix(&git_config, "sonic-git.discovery.") {
+            match name.as_str() {
+                "maxdepth" => {
+                    discovery_max_depth = parse_or_warn(
+                        value
+                            .parse()
+                            .with_context(|| format!("Invalid discovery.maxDepth: {}", value)),
+                    )
+                }
+                "ignore" => discovery_ignore.push(value),
+                _ => {}
+            }
+        }
+
+        Ok(Config {
+            root,
+            layout,
+            layout_template,
+            aliases,
+            default_host,
+            host_aliases,
+            protocol,
+            host_protocols,
+            clone,
+            host_clone,
+            discovery_max_depth,
+            discovery_ignore,
+            remote,
+            fork_placement,
+            identities,
+            groups,
+            archive_dir,
+            templates,
+            templates_dir,
+            post_clone_hooks,
+            trusted_hooks,
+            index_path: discovery::index_path(),
+            history_path: history::history_path(),
+        })
+    }
+
+    /// Remote that identifies a repository: `sonic-git.remote`, or `origin`.
+    pub fn remote_name(&self) -> &str {
+        self.remote.as_deref().unwrap_or("origin")
+    }
+
+    /// Clone protocol for `host`: the per-host setting, then the global one.
+    pub fn protocol_for(&self, host: &str) -> Protocol {
+        self.host_protocols
+            .get(&host.to_ascii_lowercase())
+            .copied()
+            .or(self.protocol)
+            .unwrap_or_default()
+    }
+
+    /// The identity for a repository: of all profiles with a pattern matching
+    /// `repo_path`, the one with the most specific pattern.
+    pub fn identity_for(&self, repo_path: &str) -> Option<&Identity> {
+        self.identities
+            .iter()
+            .filter_map(|identity| {
+                identity
+                    .patterns
+                    .iter()
+                    .filter(|pattern| pattern::matches(pattern, repo_path))
+                    .map(|pattern| pattern::specificity(pattern))
+                    .max()
+                    .map(|specificity| (specificity, identity))
+            })
+            .max_by_key(|(specificity, _)| *specificity)
+            .map(|(_, identity)| identity)
+    }
+
+    /// Post-clone commands for a repository, global ones first, in the
+    /// order they are configured.
+    pub fn post_clone_hooks_for(&self, repo_path: &str) -> Vec<&str> {
+        let global = self.post_clone_hooks.iter().filter(|h| h.pattern.is_none());
+        let matching = self.post_clone_hooks.iter().filter(|h| {
+            h.pattern
+                .as_deref()
+                .is_some_and(|pattern| pattern::matches(pattern, repo_path))
+        });
+        global.chain(matching).map(|h| h.command.as_str()).collect()
+    }
+
+    /// Whether a repository's own post-clone script runs without asking.
+    pub fn trusts_hooks_of(&self, repo_path: &str) -> bool {
+        self.trusted_hooks
+            .iter()
+            .any(|pattern| pattern::matches(pattern, repo_path))
+    }
+
+    /// Clone defaults for `host`: per-host settings over global ones.
+    pub fn clone_options_for(&self, host: &str) -> CloneOptions {
+        match self.host_clone.get(&host.to_ascii_lowercase()) {
+            Some(options) => options.clone().or(&self.clone),
+            None => self.clone.clone(),
+        }
     }
 
     pub fn resolve_alias(&self, cmd: &str) -> Option<Vec<String>> {
@@ -44,6 +493,52 @@ impl Config {
     }
 }
 
+/// Entries under `prefix`, keyed by the remainder of their name.
+///
+/// git lowercases section and variable names but keeps subsections as
+/// written, so `sonic-git.host.GitHub.com.protocol` yields
+/// `GitHub.com.protocol`.
+fn entries_with_prefix(git_config: &GitConfig, prefix: &str) -> Vec<(String, String)> {
+    let mut result = Vec::new();
+    let pattern = format!("{}.*", regex_escape(prefix));
+
+    if let Ok(mut entries) = git_config.entries(Some(&pattern)) {
+        while let Some(entry) = entries.next() {
+            if let Ok(entry) = entry {
+                if let (Some(name), Some(value)) = (entry.name(), entry.value()) {
+                    if let Some(rest) = name.strip_prefix(prefix) {
+                        result.push((rest.to_string(), value.to_string()));
+                    }
+                }
+            }
+        }
+    }
+
+    result
+}
+
+fn regex_escape(value: &str) -> String {
+    value.replace('.', "\\.")
+}
+
+fn parse_bool(value: &str) -> Result<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" | "yes" | "on" | "1" => Ok(true),
+        "false" | "no" | "off" | "0" => Ok(false),
+        other => anyhow::bail!("Invalid boolean: {}", other),
+    }
+}
+
+fn parse_or_warn<T>(result: Result<T>) -> Option<T> {
+    match result {
+        Ok(value) => Some(value),
+        Err(e) => {
+            eprintln!("warning: {}", e);
+            None
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -60,8 +555,8 @@ mod tests {
         aliases.insert("s".to_string(), "sonic-switch -i".to_string());
 
         let config = Config {
-            root: None,
             aliases,
+            ..Default::default()
         };
 
         let resolved = config.resolve_alias("s");
@@ -70,4 +565,100 @@ mod tests {
             Some(vec!["sonic-switch".to_string(), "-i".to_string()])
         );
     }
+
+    #[test]
+    fn test_protocol_for() {
+        let mut host_protocols = HashMap::new();
+        host_protocols.insert("gitlab.example.com".to_string(), Protocol::Ssh);
+
+        let config = Config {
+            host_protocols,
+            ..Default::default()
+        };
+        assert_eq!(config.protocol_for("GitLab.example.com"), Protocol::Ssh);
+        assert_eq!(config.protocol_for("github.com"), Protocol::Https);
+
+        let config = Config {
+            protocol: Some(Protocol::Ssh),
+            ..Default::default()
+        };
+        assert_eq!(config.protocol_for("github.com"), Protocol::Ssh);
+    }
+
+    #[test]
+    fn test_clone_options_for_host() {
+        let mut global = CloneOptions::default();
+        global.set("filter", "blob:none").unwrap();
+        global.set("depth", "10").unwrap();
+
+        let mut host = CloneOptions::default();
+        host.set("depth", "1").unwrap();
+        host.set("recurseSubmodules", "true").unwrap();
+
+        let mut host_clone = HashMap::new();
+        host_clone.insert("github.com".to_string(), host);
+
+        let config = Config {
+            clone: global,
+            host_clone,
+            ..Default::default()
+        };
+
+        let options = config.clone_options_for("GitHub.com");
+        assert_eq!(options.filter.as_deref(), Some("blob:none"));
+        assert_eq!(options.depth, Some(1));
+        assert_eq!(options.recurse_submodules, Some(true));
+        assert_eq!(config.clone_options_for("gitlab.com").depth, Some(10));
+
+        let cli = CloneOptions {
+            single_branch: Some(false),
+            recurse_submodules: Some(false),
+            ..Default::default()
+        };
+        let merged = cli.or(&config.clone_options_for("github.com"));
+        assert_eq!(
+            merged.args(),
+            vec!["--filter=blob:none", "--depth=1", "--no-single-branch"]
+        );
+
+        let cli = CloneOptions {
+            depth: Some(5),
+            single_branch: Some(true),
+            ..Default::default()
+        };
+        let merged = cli.or(&config.clone_options_for("github.com"));
+        assert_eq!(
+            merged.args(),
+            vec![
+                "--filter=blob:none",
+                "--depth=5",
+                "--single-branch",
+                "--recurse-submodules"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_clone_options_invalid() {
+        let mut options = CloneOptions::default();
+        assert!(options.set("depth", "deep").is_err());
+        assert!(options.set("singleBranch", "maybe").is_err());
+        assert!(options.set("mirror", "true").is_err());
+    }
+
+    #[test]
+    fn test_clone_layout_parse() {
+        assert_eq!(CloneLayout::parse("bare").unwrap(), CloneLayout::Bare);
+        assert_eq!(
+            CloneLayout::parse("Standard").unwrap(),
+            CloneLayout::Standard
+        );
+        assert!(CloneLayout::parse("flat").is_err());
+    }
+
+    #[test]
+    fn test_fork_placement_parse() {
+        assert_eq!(ForkPlacement::parse("Fork").unwrap(), ForkPlacement::Fork);
+        assert!(ForkPlacement::parse("origin").is_err());
+    }
 }
@@ -0,0 +1,410 @@
+use anyhow::{Context, Result};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::config::Config;
+use crate::layout::Layout;
+use crate::pool;
+
+/// Deep enough for nested GitLab groups under `{host}/{owner}/{repo}`.
+pub const DEFAULT_MAX_DEPTH: usize = 8;
+
+/// Directory names never descended into, on top of `sonic-git.discovery.ignore`.
+const DEFAULT_IGNORE: &[&str] = &["node_modules"];
+
+/// Repositories under the src root, read from the index when no directory
+/// it depends on has changed since the last walk.
+///
+/// The index lives at `config.index_path`; without one, every call walks.
+/// `refresh` forces a new walk.
+pub fn find_repositories(config: &Config, refresh: bool) -> Result<Vec<PathBuf>> {
+    let root = Layout::new(config)?.root;
+    let walker = Walker::new(config);
+    let key = walker.key(&root);
+    let index_path = config.index_path.as_deref();
+
+    if !refresh {
+        if let Some(index) = index_path.and_then(Index::load) {
+            if index.key == key && index.is_fresh() {
+                return Ok(index.repos);
+            }
+        }
+    }
+
+    let index = Index {
+        key,
+        ..walker.walk(&root)
+    };
+
+    if let Some(path) = index_path {
+        if let Err(e) = index.save(path) {
+            eprintln!("warning: {}", e);
+        }
+    }
+
+    Ok(index.repos)
+}
+
+/// `$XDG_CACHE_HOME/sonic-git/repos` (or `~/.cache/sonic-git/repos`).
+pub fn index_path() -> Option<PathBuf> {
+    let cache_home = env::var("XDG_CACHE_HOME")
+        .ok()
+        .filter(|dir| Path::new(dir).is_absolute())
+        .map(PathBuf::from)
+        .or_else(|| {
+            env::var("HOME")
+                .ok()
+                .map(|home| Path::new(&home).join(".cache"))
+        })?;
+
+    Some(cache_home.join("sonic-git").join("repos"))
+}
+
+/// Parallel, depth-limited search for repositories.
+///
+/// Hidden and ignored directories are skipped, and a repository's own
+/// contents are never searched.
+pub struct Walker {
+    max_depth: usize,
+    ignore: Vec<String>,
+    jobs: usize,
+}
+
+impl Walker {
+    pub fn new(config: &Config) -> Self {
+        let mut ignore: Vec<String> = DEFAULT_IGNORE.iter().map(|s| s.to_string()).collect();
+        ignore.extend(config.discovery_ignore.iter().cloned());
+
+        Self {
+            max_depth: config.discovery_max_depth.unwrap_or(DEFAULT_MAX_DEPTH),
+            ignore,
+            jobs: pool::default_jobs(),
+        }
+    }
+
+    /// Identifies the root and settings an index was built with.
+    fn key(&self, root: &Path) -> String {
+        format!(
+            "{}\t{}\t{}",
+            self.max_depth,
+            self.ignore.join(","),
+            root.display()
+        )
+    }
+
+    /// Walk level by level, visiting each level's directories in parallel.
+    pub fn walk(&self, root: &Path) -> Index {
+        let mut index = Index::default();
+        let mut level = vec![(root.to_path_buf(), 0)];
+
+        while !level.is_empty() {
+            let visits = pool::run(
+                &level,
+                self.jobs,
+                |(dir, depth)| self.visit(dir, *depth),
+                |_, _, _| {},
+            );
+
+            let mut next = Vec::new();
+            for ((dir, depth), visit) in level.into_iter().zip(visits) {
+                match visit {
+                    Visit::Repository => index.repos.push(dir),
+                    Visit::Directory { mtime, children } => {
+                        index.dirs.push((dir, mtime));
+                        next.extend(children.into_iter().map(|child| (child, depth + 1)));
+                    }
+                    Visit::Unreadable => {}
+                }
+            }
+            level = next;
+        }
+
+        index.repos.sort();
+        index.dirs.sort();
+        index
+    }
+
+    fn visit(&self, dir: &Path, depth: usize) -> Visit {
+        if depth > 0 && dir.join(".git").exists() {
+            return Visit::Repository;
+        }
+
+        let Some(mtime) = mtime(dir) else {
+            return Visit::Unreadable;
+        };
+
+        let mut children = Vec::new();
+        if depth < self.max_depth {
+            if let Ok(entries) = fs::read_dir(dir) {
+                for entry in entries.flatten() {
+                    let name = entry.file_name();
+                    let name = name.to_string_lossy();
+                    if name.starts_with('.') || self.ignore.iter().any(|i| *i == name) {
+                        continue;
+                    }
+
+                    let path = entry.path();
+                    if path.is_dir() {
+                        children.push(path);
+                    }
+                }
+            }
+        }
+
+        Visit::Directory { mtime, children }
+    }
+}
+
+enum Visit {
+    Repository,
+    Directory { mtime: u128, children: Vec<PathBuf> },
+    Unreadable,
+}
+
+/// Result of a walk: the repositories found and the modification time of
+/// every directory searched. Creating, removing or renaming anything in one
+/// of those directories changes its mtime and invalidates the index.
+#[derive(Debug, Default, PartialEq)]
+pub struct Index {
+    key: String,
+    pub repos: Vec<PathBuf>,
+    dirs: Vec<(PathBuf, u128)>,
+}
+
+impl Index {
+    fn is_fresh(&self) -> bool {
+        self.dirs
+            .iter()
+            .all(|(dir, time)| mtime(dir) == Some(*time))
+            && self.repos.iter().all(|repo| repo.join(".git").exists())
+    }
+
+    fn load(path: &Path) -> Option<Self> {
+        let content = fs::read_to_string(path).ok()?;
+        let mut lines = content.lines();
+        let mut index = Index {
+            key: lines.next()?.strip_prefix("key\t")?.to_string(),
+            ..Default::default()
+        };
+
+        for line in lines {
+            match line.split_once('\t')? {
+                ("repo", repo) => index.repos.push(PathBuf::from(repo)),
+                ("dir", rest) => {
+                    let (time, dir) = rest.split_once('\t')?;
+                    index.dirs.push((PathBuf::from(dir), time.parse().ok()?));
+                }
+                _ => return None,
+            }
+        }
+
+        Some(index)
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        let mut content = format!("key\t{}\n", self.key);
+        for (dir, time) in &self.dirs {
+            content.push_str(&format!("dir\t{}\t{}\n", time, dir.display()));
+        }
+        for repo in &self.repos {
+            content.push_str(&format!("repo\t{}\n", repo.display()));
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_context(|| {
+                format!("Failed to create index directory: {}", parent.display())
+            })?;
+        }
+
+        // Write then rename so a concurrent reader never sees half an index.
+        let tmp = path.with_extension(format!("tmp.{}", std::process::id()));
+        fs::write(&tmp, content)
+            .and_then(|()| fs::rename(&tmp, path))
+            .with_context(|| format!("Failed to write repository index: {}", path.display()))
+    }
+}
+
+fn mtime(dir: &Path) -> Option<u128> {
+    fs::metadata(dir)
+        .and_then(|meta| meta.modified())
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+    use std::time::Instant;
+
+    fn walker(max_depth: usize) -> Walker {
+        Walker::new(&Config {
+            discovery_max_depth: Some(max_depth),
+            discovery_ignore: vec!["vendor".to_string()],
+            ..Default::default()
+        })
+    }
+
+    fn fake_repo(dir: &Path) {
+        fs::create_dir_all(dir.join(".git")).unwrap();
+    }
+
+    #[test]
+    fn test_walk_skips_hidden_ignored_and_deep_dirs() {
+        let tmp = temp_dir("discovery-walk");
+        fake_repo(&tmp.join("github.com/poi2/g"));
+        fake_repo(&tmp.join("github.com/poi2/g/nested/inner"));
+        fake_repo(&tmp.join("gitlab.com/a/b/c/repo"));
+        fake_repo(&tmp.join("example.com/project/node_modules/dep"));
+        fake_repo(&tmp.join("example.com/vendor/lib"));
+        fake_repo(&tmp.join(".worktrees/github.com/poi2/g/main"));
+
+        let index = walker(5).walk(&tmp);
+        assert_eq!(
+            index.repos,
+            vec![
+                tmp.join("github.com/poi2/g"),
+                tmp.join("gitlab.com/a/b/c/repo")
+            ]
+        );
+
+        let index = walker(3).walk(&tmp);
+        assert_eq!(index.repos, vec![tmp.join("github.com/poi2/g")]);
+
+        fs::remove_dir_all(&tmp).ok();
+    }
+
+    #[test]
+    fn test_index_round_trip_and_invalidation() {
+        let tmp = temp_dir("discovery-index");
+        let root = tmp.join("src");
+        fake_repo(&root.join("github.com/poi2/g"));
+
+        let walker = walker(DEFAULT_MAX_DEPTH);
+        let index = Index {
+            key: walker.key(&root),
+            ..walker.walk(&root)
+        };
+        let path = tmp.join("cache/repos");
+        index.save(&path).unwrap();
+
+        let loaded = Index::load(&path).unwrap();
+        assert_eq!(loaded, index);
+        assert!(loaded.is_fresh());
+
+        fake_repo(&root.join("github.com/poi2/other"));
+        assert!(!loaded.is_fresh());
+
+        let index = walker.walk(&root);
+        assert!(index.is_fresh());
+        fs::remove_dir_all(root.join("github.com/poi2/g/.git")).unwrap();
+        assert!(!index.is_fresh());
+
+        fs::remove_dir_all(&tmp).ok();
+    }
+
+    #[test]
+    fn test_find_repositories_reads_and_writes_index() {
+        let tmp = temp_dir("discovery-find");
+        let root = tmp.join("src");
+        fake_repo(&root.join("github.com/poi2/g"));
+        fake_repo(&root.join("github.com/poi2/other"));
+
+        let path = tmp.join("cache/repos");
+        let config = Config {
+            root: Some(root.display().to_string()),
+            index_path: Some(path.clone()),
+            ..Default::default()
+        };
+        let repos = find_repositories(&config, false).unwrap();
+        assert_eq!(repos.len(), 2);
+
+        // A fresh index is answered as saved, until a refresh walks again.
+        let mut index = Index::load(&path).unwrap();
+        index.repos.pop();
+        index.save(&path).unwrap();
+        assert_eq!(find_repositories(&config, false).unwrap().len(), 1);
+        assert_eq!(find_repositories(&config, true).unwrap(), repos);
+        assert_eq!(Index::load(&path).unwrap().repos, repos);
+
+        fs::remove_dir_all(&tmp).ok();
+    }
+
+    /// The recursive walker discovery replaced, kept for comparison.
+    fn sequential_walk(dir: &Path, repos: &mut Vec<PathBuf>) {
+        if dir.join(".git").exists() {
+            repos.push(dir.to_path_buf());
+            return;
+        }
+
+        if let Ok(entries) = fs::read_dir(dir) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.is_dir() && !entry.file_name().to_string_lossy().starts_with('.') {
+                    sequential_walk(&path, repos);
+                }
+            }
+        }
+    }
+
+    /// Prints the timings rather than asserting on them, which would depend
+    /// on the machine and its load.
+    ///
+    /// `cargo test --release -- --ignored bench_discovery --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_discovery() {
+        let tmp = temp_dir("discovery-bench");
+        let root = tmp.join("src");
+
+        for org in 0..10 {
+            for repo in 0..20 {
+                fake_repo(&root.join(format!("github.com/org{}/repo{}", org, repo)));
+            }
+            // A checkout without .git, e.g. an unpacked tarball with
+            // installed dependencies.
+            for a in 0..10 {
+                for b in 0..10 {
+                    for c in 0..5 {
+                        let dir = root.join(format!(
+                            "example.com/org{}/app/node_modules/p{}/node_modules/q{}/lib{}",
+                            org, a, b, c
+                        ));
+                        fs::create_dir_all(dir).unwrap();
+                    }
+                }
+            }
+        }
+
+        let start = Instant::now();
+        let mut expected = Vec::new();
+        sequential_walk(&root, &mut expected);
+        expected.sort();
+        let sequential = start.elapsed();
+
+        let walker = walker(DEFAULT_MAX_DEPTH);
+        let start = Instant::now();
+        let index = Index {
+            key: walker.key(&root),
+            ..walker.walk(&root)
+        };
+        let parallel = start.elapsed();
+        assert_eq!(index.repos, expected);
+
+        let path = tmp.join("cache/repos");
+        index.save(&path).unwrap();
+        let start = Instant::now();
+        let cached = Index::load(&path).filter(|i| i.is_fresh()).unwrap();
+        let indexed = start.elapsed();
+        assert_eq!(cached.repos, expected);
+
+        println!("sequential walk: {:?}", sequential);
+        println!("parallel walk:   {:?}", parallel);
+        println!("cached index:    {:?}", indexed);
+
+        fs::remove_dir_all(&tmp).ok();
+    }
+}
@@ -0,0 +1,364 @@
+use anyhow::Result;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+use crate::config::Config;
+use crate::discovery;
+use crate::layout::Layout;
+use crate::pool;
+use crate::repo::RepoInfo;
+use crate::repository;
+use crate::selection::Selection;
+use crate::worktree::Worktree;
+
+/// A directory the command runs in, named by its repository or branch.
+struct Target {
+    name: String,
+    dir: PathBuf,
+}
+
+#[derive(Debug, PartialEq)]
+enum Outcome {
+    /// The exit code, or `None` when the command was killed by a signal.
+    Exited(Option<i32>),
+    Failed(String),
+    /// Not started because an earlier command failed under `--fail-fast`.
+    Skipped,
+}
+
+impl Outcome {
+    fn is_success(&self) -> bool {
+        *self == Outcome::Exited(Some(0))
+    }
+
+    fn label(&self) -> String {
+        match self {
+            Outcome::Exited(Some(code)) => format!("exit {}", code),
+            Outcome::Exited(None) => "signal".to_string(),
+            Outcome::Failed(_) => "failed".to_string(),
+            Outcome::Skipped => "skipped".to_string(),
+        }
+    }
+
+    fn symbol(&self) -> &'static str {
+        match self {
+            _ if self.is_success() => "✓",
+            Outcome::Skipped => "!",
+            _ => "✗",
+        }
+    }
+}
+
+/// Run `command` in every selected repository, or in the worktrees of those
+/// repositories with `worktrees`. Without a selection the worktrees of the
+/// current repository are used.
+pub fn foreach(
+    config: &Config,
+    selection: Option<&Selection>,
+    worktrees: bool,
+    jobs: usize,
+    fail_fast: bool,
+    command: &[String],
+) -> Result<()> {
+    let targets = find_targets(config, selection, worktrees)?;
+
+    if targets.is_empty() {
+        println!("Nothing to run in");
+        return Ok(());
+    }
+
+    let width = targets
+        .iter()
+        .map(|t| t.name.chars().count())
+        .max()
+        .unwrap_or(0);
+    let total = targets.len();
+    let count_width = total.to_string().len();
+    let stop = AtomicBool::new(false);
+
+    let outcomes = pool::run(
+        &targets,
+        jobs,
+        |target| {
+            if stop.load(Ordering::SeqCst) {
+                return Outcome::Skipped;
+            }
+            let outcome = run_command(&target.dir, command, &format!("{:<width$}", target.name));
+            if fail_fast && !outcome.is_success() {
+                stop.store(true, Ordering::SeqCst);
+            }
+            outcome
+        },
+        |done, target, outcome| {
+            let detail = match outcome {
+                Outcome::Failed(e) => format!(" ({})", e),
+                _ => String::new(),
+            };
+            println!(
+                "[{:>count_width$}/{}] {} {} {}{}",
+                done,
+                total,
+                outcome.symbol(),
+                target.name,
+                outcome.label(),
+                detail,
+            );
+        },
+    );
+
+    print_summary(&targets, &outcomes);
+
+    let skipped = outcomes.iter().filter(|o| **o == Outcome::Skipped).count();
+    let failed = outcomes.iter().filter(|o| !o.is_success()).count() - skipped;
+    if skipped > 0 {
+        anyhow::bail!(
+            "{} of {} commands failed, {} not started",
+            failed,
+            total,
+            skipped
+        );
+    }
+    if failed > 0 {
+        anyhow::bail!("{} of {} commands failed", failed, total);
+    }
+
+    Ok(())
+}
+
+fn find_targets(
+    config: &Config,
+    selection: Option<&Selection>,
+    worktrees: bool,
+) -> Result<Vec<Target>> {
+    let layout = Layout::new(config)?;
+
+    let Some(selection) = selection else {
+        // The worktrees of the current repository, named by branch alone.
+        let repo_info = RepoInfo::detect(&layout, config.remote_name())?;
+        return worktree_targets(&repo_info.main_repo_dir, None);
+    };
+
+    let repos = selection.filter(config, discovery::find_repositories(config, false)?)?;
+    let mut targets = Vec::new();
+
+    for dir in repos {
+        let target = repository_target(&layout, &dir)?;
+        if worktrees {
+            targets.extend(worktree_targets(&dir, Some(&target.name))?);
+        } else {
+            targets.push(target);
+        }
+    }
+
+    Ok(targets)
+}
+
+/// A repository named by its path under the root. A bare hub runs in the
+/// worktree of its default branch, as `switch` lands there.
+fn repository_target(layout: &Layout, repo_dir: &Path) -> Result<Target> {
+    Ok(Target {
+        name: repo_dir
+            .strip_prefix(&layout.root)
+            .unwrap_or(repo_dir)
+            .display()
+            .to_string(),
+        dir: repository::resolve_checkout(repo_dir)?,
+    })
+}
+
+/// Checked-out worktrees of a repository, as `branch` or `repo:branch`.
+fn worktree_targets(repo_dir: &Path, repo_name: Option<&str>) -> Result<Vec<Target>> {
+    Ok(Worktree::list(&repo_dir.to_path_buf())?
+        .into_iter()
+        .filter(|wt| !wt.is_bare)
+        .map(|wt| {
+            let branch = wt.branch.unwrap_or_else(|| {
+                let dir = wt.path.file_name().unwrap_or_default().to_string_lossy();
+                format!("({})", dir)
+            });
+            Target {
+                name: match repo_name {
+                    Some(repo) => format!("{}:{}", repo, branch),
+                    None => branch,
+                },
+                dir: wt.path,
+            }
+        })
+        .collect())
+}
+
+/// Run `command` in `dir`, printing each line of its output as
+/// `[prefix] line`. A single argument is a shell command line.
+fn run_command(dir: &Path, command: &[String], prefix: &str) -> Outcome {
+    let mut cmd = match command {
+        [line] => {
+            let mut cmd = Command::new("sh");
+            cmd.args(["-c", line]);
+            cmd
+        }
+        [program, args @ ..] => {
+            let mut cmd = Command::new(program);
+            cmd.args(args);
+            cmd
+        }
+        [] => return Outcome::Failed("no command given".to_string()),
+    };
+
+    let child = cmd
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) => return Outcome::Failed(e.to_string()),
+    };
+
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    thread::scope(|scope| {
+        if let Some(stderr) = stderr {
+            scope.spawn(|| forward(stderr, prefix, true));
+        }
+        if let Some(stdout) = stdout {
+            forward(stdout, prefix, false);
+        }
+    });
+
+    match child.wait() {
+        Ok(status) => Outcome::Exited(status.code()),
+        Err(e) => Outcome::Failed(e.to_string()),
+    }
+}
+
+fn forward(stream: impl Read, prefix: &str, to_stderr: bool) {
+    for line in BufReader::new(stream).split(b'\n').map_while(Result::ok) {
+        let line = String::from_utf8_lossy(&line);
+        let line = line.trim_end_matches('\r');
+        if to_stderr {
+            eprintln!("[{}] {}", prefix, line);
+        } else {
+            println!("[{}] {}", prefix, line);
+        }
+    }
+}
+
+fn print_summary(targets: &[Target], outcomes: &[Outcome]) {
+    let mut labels: Vec<String> = outcomes.iter().map(Outcome::label).collect();
+    labels.sort();
+    labels.dedup();
+
+    println!();
+    println!("{:<12} {:>5}", "STATUS", "COUNT");
+
+    for label in labels {
+        let matching: Vec<(&Target, &Outcome)> = targets
+            .iter()
+            .zip(outcomes)
+            .filter(|(_, o)| o.label() == label)
+            .collect();
+        println!("{:<12} {:>5}", label, matching.len());
+
+        for (target, outcome) in matching {
+            match outcome {
+                _ if outcome.is_success() => {}
+                Outcome::Failed(e) => println!("  {}: {}", target.name, e),
+                _ => println!("  {}", target.name),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::run_git;
+    use crate::test_support::{init_repo, temp_dir};
+    use std::fs;
+
+    #[test]
+    fn test_run_command_and_worktree_targets() {
+        let tmp = temp_dir("foreach");
+        let repo = tmp.join("repo");
+        init_repo(&repo, &["a.txt"]);
+        let feature = tmp.join("feature");
+        run_git(
+            &repo,
+            &[
+                "worktree",
+                "add",
+                "--quiet",
+                "-b",
+                "feature",
+                feature.to_str().unwrap(),
+            ],
+        )
+        .unwrap();
+
+        let names: Vec<String> = worktree_targets(&repo, Some("org/repo"))
+            .unwrap()
+            .into_iter()
+            .map(|t| t.name)
+            .collect();
+        assert_eq!(names, vec!["org/repo:main", "org/repo:feature"]);
+
+        let shell = |line: &str| run_command(&feature, &[line.to_string()], "feature");
+        assert_eq!(shell("test -f a.txt && echo ok"), Outcome::Exited(Some(0)));
+        assert_eq!(shell("exit 3"), Outcome::Exited(Some(3)));
+
+        let argv = ["git".to_string(), "status".to_string()];
+        assert!(run_command(&feature, &argv, "feature").is_success());
+        assert!(matches!(
+            run_command(
+                &feature,
+                &["/nonexistent/cmd".to_string(), "x".to_string()],
+                "x"
+            ),
+            Outcome::Failed(_)
+        ));
+
+        fs::remove_dir_all(&tmp).ok();
+    }
+
+    #[test]
+    fn test_run_command_in_bare_hub() {
+        let tmp = temp_dir("foreach-bare");
+        let remote = tmp.join("remote");
+        init_repo(&remote, &["a.txt"]);
+        let hub = tmp.join("hub");
+        run_git(
+            &tmp,
+            &[
+                "clone",
+                "--quiet",
+                "--bare",
+                remote.to_str().unwrap(),
+                hub.join(".git").to_str().unwrap(),
+            ],
+        )
+        .unwrap();
+        let main = tmp.join("main");
+        run_git(
+            &hub,
+            &["worktree", "add", "--quiet", main.to_str().unwrap(), "main"],
+        )
+        .unwrap();
+
+        let status = ["git".to_string(), "status".to_string()];
+        assert_eq!(
+            run_command(&hub, &status, "hub"),
+            Outcome::Exited(Some(128))
+        );
+        let layout = Layout::with_template(tmp.clone(), "{host}/{owner}/{repo}").unwrap();
+        let target = repository_target(&layout, &hub).unwrap();
+        assert_eq!(target.name, "hub");
+        assert_eq!(target.dir, main);
+        assert!(run_command(&target.dir, &status, "hub").is_success());
+
+        fs::remove_dir_all(&tmp).ok();
+    }
+}
@@ -0,0 +1,240 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::layout::Layout;
+use crate::pool;
+use crate::relocate;
+use crate::repo;
+use crate::repository;
+use crate::worktree::Worktree;
+
+/// A directory under the worktree base that no repository owns any more.
+struct Orphan {
+    path: PathBuf,
+    reason: String,
+    size: u64,
+}
+
+/// Find worktree directories under `dir` whose repository is gone or no
+/// longer lists them, and report their sizes. Nothing is removed unless
+/// `delete` is set, and then only after confirmation (or with `yes`).
+pub fn collect_garbage(layout: &Layout, dir: &Path, delete: bool, yes: bool) -> Result<()> {
+    let mut candidates = Vec::new();
+    find_worktree_dirs(dir, &mut candidates);
+
+    let checked = pool::run(
+        &candidates,
+        pool::default_jobs(),
+        |path| orphan_reason(path).map(|reason| (reason, dir_size(path))),
+        |_, _, _| {},
+    );
+    let orphans: Vec<Orphan> = candidates
+        .into_iter()
+        .zip(checked)
+        .filter_map(|(path, found)| found.map(|(reason, size)| Orphan { path, reason, size }))
+        .collect();
+
+    if orphans.is_empty() {
+        println!("No orphaned worktrees under {}", dir.display());
+        return Ok(());
+    }
+
+    for orphan in &orphans {
+        println!(
+            "{:>10}  {} ({})",
+            format_size(orphan.size),
+            orphan.path.display(),
+            orphan.reason
+        );
+    }
+    let total = orphans.iter().map(|o| o.size).sum();
+    println!(
+        "{} orphaned worktree(s), {}",
+        orphans.len(),
+        format_size(total)
+    );
+
+    if !delete {
+        println!("Dry run: nothing deleted (use --delete to remove them)");
+        return Ok(());
+    }
+
+    if !yes && !repository::confirm(&format!("Delete {} orphaned worktree(s)?", orphans.len()))? {
+        println!("Cancelled");
+        return Ok(());
+    }
+
+    let worktree_base = layout.worktree_base();
+    for orphan in &orphans {
+        fs::remove_dir_all(&orphan.path)
+            .with_context(|| format!("Failed to delete {}", orphan.path.display()))?;
+        relocate::remove_empty_parents(&orphan.path, &worktree_base);
+        println!("✓ Deleted: {}", orphan.path.display());
+    }
+    println!("✓ Freed {}", format_size(total));
+
+    Ok(())
+}
+
+/// Directories with a `.git` file, i.e. linked worktrees. A directory with a
+/// `.git` directory is a repository of its own and is left alone.
+fn find_worktree_dirs(dir: &Path, found: &mut Vec<PathBuf>) {
+    let dot_git = dir.join(".git");
+    if dot_git.is_file() {
+        found.push(dir.to_path_buf());
+        return;
+    }
+    if dot_git.exists() {
+        return;
+    }
+
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            find_worktree_dirs(&entry.path(), found);
+        }
+    }
+}
+
+/// Why the worktree at `dir` is orphaned, or `None` while its repository
+/// still lists it. When the repository cannot be read, the worktree is kept.
+fn orphan_reason(dir: &Path) -> Option<String> {
+    let content = fs::read_to_string(dir.join(".git")).ok()?;
+    let Some(gitdir) = content.trim().strip_prefix("gitdir:") else {
+        return Some("invalid .git file".to_string());
+    };
+
+    let gitdir = dir.join(gitdir.trim());
+    if !gitdir.is_dir() {
+        return Some(format!("gitdir missing: {}", gitdir.display()));
+    }
+
+    // `<common dir>/worktrees/<name>` names its repository in `commondir`.
+    let common_dir = match fs::read_to_string(gitdir.join("commondir")) {
+        Ok(common_dir) => gitdir.join(common_dir.trim()),
+        Err(_) => gitdir.clone(),
+    };
+    let common_dir = fs::canonicalize(&common_dir).unwrap_or(common_dir);
+
+    let path = fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+    let listed = Worktree::list(&common_dir)
+        .ok()?
+        .into_iter()
+        .any(|wt| fs::canonicalize(&wt.path).unwrap_or(wt.path) == path);
+
+    if listed {
+        return None;
+    }
+    Some(format!(
+        "not a worktree of {}",
+        repo::hub_dir(&common_dir).display()
+    ))
+}
+
+/// Bytes used by the files under `path`, without following symlinks.
+fn dir_size(path: &Path) -> u64 {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+
+    fs::read_dir(path)
+        .map(|entries| entries.flatten().map(|e| dir_size(&e.path())).sum())
+        .unwrap_or(0)
+}
+
+/// Human-readable size such as `512 B`, `4.0 KiB` or `1.5 GiB`.
+fn format_size(bytes: u64) -> String {
+    const UNITS: &[(u64, &str)] = &[
+        (1 << 40, "TiB"),
+        (1 << 30, "GiB"),
+        (1 << 20, "MiB"),
+        (1 << 10, "KiB"),
+    ];
+
+    UNITS
+        .iter()
+        .find(|(unit, _)| bytes >= *unit)
+        .map(|(unit, suffix)| format!("{:.1} {}", bytes as f64 / *unit as f64, suffix))
+        .unwrap_or_else(|| format!("{} B", bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::run_git;
+    use crate::test_support::{init_repo, temp_dir};
+
+    #[test]
+    fn test_find_orphans() {
+        let tmp = temp_dir("gc");
+        let repo = tmp.join("src/repo");
+        init_repo(&repo, &["a.txt"]);
+
+        let base = tmp.join("worktrees");
+        for branch in ["kept", "unlisted"] {
+            run_git(
+                &repo,
+                &[
+                    "worktree",
+                    "add",
+                    "--quiet",
+                    "-b",
+                    branch,
+                    base.join("repo").join(branch).to_str().unwrap(),
+                ],
+            )
+            .unwrap();
+        }
+        // A copy points at the admin dir of `kept`, which lists another path.
+        fs::create_dir_all(base.join("copy")).unwrap();
+        fs::copy(base.join("repo/kept/.git"), base.join("copy/.git")).unwrap();
+        // The repository now records `unlisted` somewhere else.
+        fs::write(
+            repo.join(".git/worktrees/unlisted/gitdir"),
+            "/nonexistent/.git\n",
+        )
+        .unwrap();
+
+        let gone = base.join("gone/main");
+        fs::create_dir_all(&gone).unwrap();
+        fs::write(gone.join(".git"), "gitdir: /nonexistent/worktrees/main\n").unwrap();
+        fs::write(gone.join("big.bin"), vec![0; 2048]).unwrap();
+        init_repo(&base.join("standalone"), &[]);
+
+        let mut found = Vec::new();
+        find_worktree_dirs(&base, &mut found);
+        found.sort();
+        assert_eq!(
+            found,
+            vec![
+                base.join("copy"),
+                base.join("gone/main"),
+                base.join("repo/kept"),
+                base.join("repo/unlisted"),
+            ]
+        );
+
+        assert!(orphan_reason(&base.join("repo/kept")).is_none());
+        assert!(orphan_reason(&base.join("copy"))
+            .unwrap()
+            .starts_with("not a worktree of"));
+        assert!(orphan_reason(&base.join("repo/unlisted")).is_some());
+        assert!(orphan_reason(&gone).unwrap().starts_with("gitdir missing"));
+        assert!(dir_size(&gone) >= 2048);
+
+        fs::remove_dir_all(&tmp).ok();
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(4096), "4.0 KiB");
+        assert_eq!(format_size(3 << 29), "1.5 GiB");
+    }
+}
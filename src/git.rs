@@ -1,16 +1,101 @@
 use anyhow::{Context, Result};
-use std::env;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
+use crate::cache;
+use crate::config::{CloneLayout, CloneOptions, Config, ForkPlacement};
+use crate::hooks;
+use crate::identity;
+use crate::layout::Layout;
 use crate::path;
+use crate::repo::{RepoInfo, UPSTREAM_REMOTE};
+use crate::shorthand;
+use crate::worktree;
 
-pub fn clone_repository(url: &str) -> Result<()> {
-    let repo_path = path::parse_repo_path(url).context("Failed to parse repository URL")?;
+/// The repository a fork was made from, added as the `upstream` remote.
+pub struct Fork {
+    pub upstream_url: String,
+    pub placement: ForkPlacement,
+}
+
+/// Clone what the user typed: expand shorthands, then layer the given
+/// options over the per-host and global defaults from git config.
+///
+/// With `upstream`, `input` is a fork of it. `hooks` runs the post-clone
+/// hooks once the clone is set up.
+pub fn clone(
+    config: &Config,
+    input: &str,
+    bare: bool,
+    options: CloneOptions,
+    upstream: Option<&str>,
+    hooks: bool,
+) -> Result<()> {
+    let url = shorthand::resolve_clone_url(input, config)?;
+    if url != input {
+        println!("Resolved {} -> {}", input, url);
+    }
+
+    let fork = match upstream {
+        Some(input) => {
+            let upstream_url = shorthand::resolve_clone_url(input, config)?;
+            if upstream_url != input {
+                println!("Resolved {} -> {}", input, upstream_url);
+            }
+            Some(Fork {
+                upstream_url,
+                placement: config.fork_placement,
+            })
+        }
+        None => None,
+    };
+
+    let mode = if bare {
+        CloneLayout::Bare
+    } else {
+        config.layout
+    };
+    let host = path::GitUrl::parse(&url)?.host;
+    let mut options = options.or(&config.clone_options_for(&host));
+
+    // An identity's key is needed to fetch, not just once the clone is done.
+    let placement_url = match fork {
+        Some(ref fork) if fork.placement == ForkPlacement::Upstream => &fork.upstream_url,
+        _ => &url,
+    };
+    if let Ok(repo_path) = path::parse_repo_path(placement_url) {
+        options.ssh_command = config
+            .identity_for(&repo_path)
+            .and_then(|identity| identity.ssh_command.clone());
+    }
 
-    let home = env::var("HOME").context("HOME environment variable not set")?;
+    let target_dir = clone_repository(&Layout::new(config)?, &url, mode, &options, fork.as_ref())?;
+    identity::apply(config, &target_dir, None)?;
 
-    let target_dir = PathBuf::from(&home).join("src").join(&repo_path);
+    if hooks {
+        hooks::run_post_clone(config, &target_dir, &url)?;
+    }
+    Ok(())
+}
+
+pub fn clone_repository(
+    layout: &Layout,
+    url: &str,
+    mode: CloneLayout,
+    options: &CloneOptions,
+    fork: Option<&Fork>,
+) -> Result<PathBuf> {
+    // Forks borrow from the upstream's cache, which every fork shares.
+    let canonical_url = fork.map_or(url, |fork| fork.upstream_url.as_str());
+    let canonical_path =
+        path::parse_repo_path(canonical_url).context("Failed to parse repository URL")?;
+
+    let repo_path = match fork {
+        Some(fork) if fork.placement == ForkPlacement::Upstream => canonical_path.clone(),
+        _ => path::parse_repo_path(url).context("Failed to parse repository URL")?,
+    };
+
+    let target_dir = layout.repo_dir(&repo_path)?;
 
     if target_dir.exists() {
         anyhow::bail!(
@@ -27,10 +112,59 @@ pub fn clone_repository(url: &str) -> Result<()> {
             .with_context(|| format!("Failed to create parent directory: {}", parent.display()))?;
     }
 
+    let reference = if options.cache == Some(true) {
+        let cache_repo = layout.cache_repo_dir(&canonical_path);
+        match cache::ensure(&cache_repo, canonical_url, options.ssh_command.as_deref()) {
+            Ok(()) => Some(cache_repo),
+            Err(e) => {
+                eprintln!(
+                    "warning: object cache unavailable, cloning without it: {}",
+                    e
+                );
+                None
+            }
+        }
+    } else {
+        None
+    };
+
     println!("Cloning {} to {}...", url, target_dir.display());
 
-    let status = Command::new("git")
-        .args(["clone", url, target_dir.to_str().unwrap()])
+    if mode == CloneLayout::Bare {
+        let default_branch = clone_bare(url, &target_dir, options, reference.as_deref())?;
+        println!(
+            "✓ Cloned bare hub to: {}",
+            target_dir.join(".git").display()
+        );
+        println!("✓ Main branch: {}", default_branch);
+        print_clone_options(options);
+        print_cache_usage(&target_dir, reference.as_deref());
+
+        if let Some(fork) = fork {
+            add_upstream(&target_dir.join(".git"), fork, &default_branch, options)?;
+        }
+
+        let repo_info = RepoInfo::new(layout, target_dir.clone(), url.to_string())?;
+        let worktree_path = worktree::create_worktree(&repo_info, &default_branch, None)?;
+
+        if options.recurse_submodules == Some(true) {
+            run_git(
+                &worktree_path,
+                &["submodule", "update", "--init", "--recursive"],
+            )?;
+        }
+
+        return Ok(target_dir);
+    }
+
+    let mut cmd = Command::new("git");
+    cmd.arg("clone").args(options.args());
+    if let Some(ref cache_repo) = reference {
+        cmd.arg("--reference-if-able").arg(cache_repo);
+    }
+
+    let status = cmd
+        .args([url, target_dir.to_str().unwrap()])
         .status()
         .context("Failed to execute git clone")?;
 
@@ -42,10 +176,220 @@ pub fn clone_repository(url: &str) -> Result<()> {
 
     let default_branch = get_default_branch(&target_dir)?;
     println!("✓ Main branch: {}", default_branch);
+    print_clone_options(options);
+    print_cache_usage(&target_dir, reference.as_deref());
+
+    if let Some(fork) = fork {
+        add_upstream(&target_dir, fork, &default_branch, options)?;
+    }
+
+    Ok(target_dir)
+}
+
+/// Add the repository a fork was made from as `upstream` and make the local
+/// default branch track the upstream's default branch.
+fn add_upstream(
+    git_dir: &Path,
+    fork: &Fork,
+    local_branch: &str,
+    options: &CloneOptions,
+) -> Result<()> {
+    run_git(
+        git_dir,
+        &["remote", "add", UPSTREAM_REMOTE, &fork.upstream_url],
+    )?;
+
+    let mut fetch = vec!["fetch".to_string(), "--quiet".to_string()];
+    if let Some(depth) = options.depth {
+        fetch.push(format!("--depth={}", depth));
+    }
+    fetch.push(UPSTREAM_REMOTE.to_string());
+    let fetch: Vec<&str> = fetch.iter().map(String::as_str).collect();
+    run_git(git_dir, &fetch)?;
+
+    run_git(git_dir, &["remote", "set-head", UPSTREAM_REMOTE, "--auto"])?;
+    let upstream_head = git_output(
+        git_dir,
+        &[
+            "symbolic-ref",
+            "--short",
+            &format!("refs/remotes/{}/HEAD", UPSTREAM_REMOTE),
+        ],
+    )?;
+
+    run_git(
+        git_dir,
+        &[
+            "branch",
+            "--quiet",
+            "--set-upstream-to",
+            &upstream_head,
+            local_branch,
+        ],
+    )?;
+
+    println!("✓ Upstream: {}", fork.upstream_url);
+    println!("✓ {} tracks {}", local_branch, upstream_head);
+    Ok(())
+}
+
+fn print_cache_usage(repo_dir: &Path, reference: Option<&Path>) {
+    if reference.is_none() {
+        return;
+    }
+
+    match cache::object_stats(repo_dir) {
+        Ok(stats) => println!(
+            "✓ Object cache: {} objects from cache, {} downloaded",
+            stats.borrowed(),
+            stats.local
+        ),
+        Err(e) => eprintln!("warning: failed to count objects: {}", e),
+    }
+}
+
+fn print_clone_options(options: &CloneOptions) {
+    if let Some(ref filter) = options.filter {
+        println!("✓ Partial clone: {}", filter);
+    }
+
+    if let Some(depth) = options.depth {
+        println!("✓ Shallow clone: depth {}", depth);
+    }
+
+    if let Some(ref branch) = options.branch {
+        println!("✓ Branch: {}", branch);
+    }
+
+    if options.single_branch == Some(true) {
+        println!("✓ Single branch");
+    }
+
+    if options.recurse_submodules == Some(true) {
+        println!("✓ Submodules: initialized recursively");
+    }
+}
+
+/// Clone `url` as a bare hub at `<target_dir>/.git` and return its default
+/// branch.
+///
+/// `git clone --bare` neither configures a fetch refspec nor creates
+/// remote-tracking branches, so both are set up here. Every local branch
+/// except the default one is dropped; worktrees recreate them from
+/// `origin/*` on demand.
+///
+/// Submodules need a work tree, so `--recurse-submodules` is left to the
+/// caller once the default branch worktree exists.
+fn clone_bare(
+    url: &str,
+    target_dir: &Path,
+    options: &CloneOptions,
+    reference: Option<&Path>,
+) -> Result<String> {
+    let git_dir = target_dir.join(".git");
+
+    let bare_options = CloneOptions {
+        recurse_submodules: None,
+        ..options.clone()
+    };
+
+    let mut cmd = Command::new("git");
+    cmd.args(["clone", "--bare"]).args(bare_options.args());
+    if let Some(cache_repo) = reference {
+        cmd.arg("--reference-if-able").arg(cache_repo);
+    }
+
+    let status = cmd
+        .args([url, git_dir.to_str().unwrap()])
+        .status()
+        .context("Failed to execute git clone --bare")?;
+
+    if !status.success() {
+        anyhow::bail!("git clone --bare failed");
+    }
+
+    let default_branch = git_output(&git_dir, &["symbolic-ref", "--short", "HEAD"])?;
+
+    let refspec = if options.single_branch == Some(true) {
+        format!("+refs/heads/{0}:refs/remotes/origin/{0}", default_branch)
+    } else {
+        "+refs/heads/*:refs/remotes/origin/*".to_string()
+    };
+    run_git(&git_dir, &["config", "remote.origin.fetch", &refspec])?;
+
+    let mut fetch = vec!["fetch".to_string(), "--quiet".to_string()];
+    if let Some(depth) = options.depth {
+        fetch.push(format!("--depth={}", depth));
+    }
+    fetch.push("origin".to_string());
+    let fetch: Vec<&str> = fetch.iter().map(String::as_str).collect();
+    run_git(&git_dir, &fetch)?;
+
+    let heads = git_output(
+        &git_dir,
+        &["for-each-ref", "--format=%(refname:short)", "refs/heads"],
+    )?;
+    for head in heads.lines().filter(|head| *head != default_branch) {
+        run_git(&git_dir, &["branch", "--quiet", "-D", head])?;
+    }
+
+    let upstream = format!("origin/{}", default_branch);
+    run_git(
+        &git_dir,
+        &[
+            "branch",
+            "--quiet",
+            "--set-upstream-to",
+            &upstream,
+            &default_branch,
+        ],
+    )?;
+
+    Ok(default_branch)
+}
+
+pub fn run_git(dir: &Path, args: &[&str]) -> Result<()> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .with_context(|| format!("Failed to execute git {}", args.join(" ")))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("git {} failed: {}", args.join(" "), stderr.trim());
+    }
 
     Ok(())
 }
 
+pub fn git_output(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .with_context(|| format!("Failed to execute git {}", args.join(" ")))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("git {} failed: {}", args.join(" "), stderr.trim());
+    }
+
+    Ok(String::from_utf8(output.stdout)?.trim().to_string())
+}
+
+/// Commits the checked-out branch is ahead of and behind its upstream, or
+/// `None` when it has no upstream (or HEAD is detached).
+pub fn ahead_behind(checkout: &Path) -> Option<(u32, u32)> {
+    let counts = git_output(
+        checkout,
+        &["rev-list", "--left-right", "--count", "HEAD...@{upstream}"],
+    )
+    .ok()?;
+    let (ahead, behind) = counts.split_once('\t')?;
+    Some((ahead.parse().ok()?, behind.parse().ok()?))
+}
+
 fn get_default_branch(repo_path: &PathBuf) -> Result<String> {
     let output = Command::new("git")
         .args(["branch", "--show-current"])
@@ -64,3 +408,177 @@ fn get_default_branch(repo_path: &PathBuf) -> Result<String> {
 
     Ok(branch)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Identity;
+    use crate::test_support::{init_repo, temp_dir};
+    use crate::worktree::Worktree;
+    use std::fs;
+
+    #[test]
+    fn test_clone_bare_sets_up_hub() {
+        let tmp = temp_dir("clone-bare");
+        let remote = tmp.join("remote");
+        init_repo(&remote, &["README.md"]);
+        run_git(&remote, &["branch", "feature"]).unwrap();
+
+        let target = tmp.join("hub");
+        let default_branch = clone_bare(
+            remote.to_str().unwrap(),
+            &target,
+            &CloneOptions::default(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(default_branch, "main");
+
+        let git_dir = target.join(".git");
+        let heads = git_output(
+            &git_dir,
+            &["for-each-ref", "--format=%(refname)", "refs/heads"],
+        )
+        .unwrap();
+        assert_eq!(heads, "refs/heads/main");
+
+        let remotes = git_output(
+            &git_dir,
+            &["for-each-ref", "--format=%(refname)", "refs/remotes"],
+        )
+        .unwrap();
+        assert!(remotes.contains("refs/remotes/origin/feature"));
+
+        let upstream =
+            git_output(&git_dir, &["rev-parse", "--abbrev-ref", "main@{upstream}"]).unwrap();
+        assert_eq!(upstream, "origin/main");
+
+        fs::remove_dir_all(&tmp).ok();
+    }
+
+    #[test]
+    fn test_clone_uses_identity_ssh_command() {
+        let tmp = temp_dir("clone-ssh");
+        let marker = tmp.join("ssh-used");
+        let config = Config {
+            root: Some(tmp.join("src").to_str().unwrap().to_string()),
+            identities: vec![Identity {
+                profile: "work".to_string(),
+                patterns: vec!["example.com/acme".to_string()],
+                ssh_command: Some(format!("touch {}; false", marker.display())),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        // The fake ssh fails the clone, but it must have been the one used.
+        assert!(clone(
+            &config,
+            "ssh://git@example.com/acme/app.git",
+            false,
+            CloneOptions::default(),
+            None,
+            false,
+        )
+        .is_err());
+        assert!(marker.exists());
+
+        fs::remove_dir_all(&tmp).ok();
+    }
+
+    #[test]
+    fn test_clone_fork_adds_upstream() {
+        let tmp = temp_dir("clone-fork");
+        let layout = Layout::with_template(tmp.join("src"), "{host}/{owner}/{repo}").unwrap();
+        let upstream = tmp.join("upstream/project");
+        init_repo(&upstream, &["README.md"]);
+        let fork = tmp.join("fork/project");
+        run_git(
+            &tmp,
+            &[
+                "clone",
+                "--quiet",
+                upstream.to_str().unwrap(),
+                fork.to_str().unwrap(),
+            ],
+        )
+        .unwrap();
+
+        let fork_info = Fork {
+            upstream_url: upstream.display().to_string(),
+            placement: ForkPlacement::Upstream,
+        };
+        clone_repository(
+            &layout,
+            fork.to_str().unwrap(),
+            CloneLayout::Standard,
+            &CloneOptions::default(),
+            Some(&fork_info),
+        )
+        .unwrap();
+
+        let target = layout
+            .repo_dir(&path::parse_repo_path(upstream.to_str().unwrap()).unwrap())
+            .unwrap();
+        assert_eq!(
+            git_output(&target, &["remote", "get-url", "origin"]).unwrap(),
+            fork.display().to_string()
+        );
+        assert_eq!(
+            git_output(&target, &["remote", "get-url", "upstream"]).unwrap(),
+            upstream.display().to_string()
+        );
+        assert_eq!(
+            git_output(&target, &["rev-parse", "--abbrev-ref", "main@{upstream}"]).unwrap(),
+            "upstream/main"
+        );
+
+        let fork_info = Fork {
+            placement: ForkPlacement::Fork,
+            ..fork_info
+        };
+        clone_repository(
+            &layout,
+            fork.to_str().unwrap(),
+            CloneLayout::Bare,
+            &CloneOptions::default(),
+            Some(&fork_info),
+        )
+        .unwrap();
+        let hub = layout
+            .repo_dir(&path::parse_repo_path(fork.to_str().unwrap()).unwrap())
+            .unwrap()
+            .join(".git");
+        assert_eq!(
+            git_output(&hub, &["rev-parse", "--abbrev-ref", "main@{upstream}"]).unwrap(),
+            "upstream/main"
+        );
+
+        // A bare hub at the upstream's path gets its worktree there too.
+        let layout = Layout::with_template(tmp.join("src-bare"), "{host}/{owner}/{repo}").unwrap();
+        let fork_info = Fork {
+            placement: ForkPlacement::Upstream,
+            ..fork_info
+        };
+        let target = clone_repository(
+            &layout,
+            fork.to_str().unwrap(),
+            CloneLayout::Bare,
+            &CloneOptions::default(),
+            Some(&fork_info),
+        )
+        .unwrap();
+        assert_eq!(
+            target,
+            layout
+                .repo_dir(&path::parse_repo_path(upstream.to_str().unwrap()).unwrap())
+                .unwrap()
+        );
+        let worktrees = Worktree::list(&target.join(".git")).unwrap();
+        assert!(worktrees
+            .iter()
+            .any(|wt| wt.branch.as_deref() == Some("main") && wt.path.join("README.md").exists()));
+
+        fs::remove_dir_all(&tmp).ok();
+    }
+}
@@ -0,0 +1,407 @@
+use anyhow::{Context, Result};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::age;
+use crate::config::Config;
+
+/// Once the ranks add up to this much, every entry decays so old favourites
+/// eventually make room for new ones.
+const MAX_TOTAL_RANK: f64 = 10_000.0;
+const DECAY: f64 = 0.9;
+
+/// How `switch -i` pickers order their entries.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum SortOrder {
+    /// By name.
+    Alpha,
+    /// Most recently switched to first.
+    Recent,
+    /// By frequency weighted by recency.
+    #[default]
+    Frecent,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub path: PathBuf,
+    pub rank: f64,
+    pub last_access: u64,
+}
+
+impl Entry {
+    /// zoxide-style frecency: visits count for more the more recent the last
+    /// one was.
+    pub fn frecency(&self, now: u64) -> f64 {
+        let age = now.saturating_sub(self.last_access);
+        let weight = if age < 3600 {
+            4.0
+        } else if age < 24 * 3600 {
+            2.0
+        } else if age < 7 * 24 * 3600 {
+            0.5
+        } else {
+            0.25
+        };
+        self.rank * weight
+    }
+}
+
+/// Usage of repositories and worktrees, stored one entry per line at
+/// `config.history_path`.
+#[derive(Debug, Default)]
+pub struct History {
+    path: Option<PathBuf>,
+    pub entries: Vec<Entry>,
+}
+
+impl History {
+    pub fn open(config: &Config) -> Result<Self> {
+        match config.history_path {
+            Some(ref path) => Self::load(path),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// The history for ordering a picker. A history that cannot be read
+    /// only costs the ordering, so it is reported and treated as empty.
+    pub fn open_for_sorting(config: &Config) -> Self {
+        Self::or_empty(Self::open(config))
+    }
+
+    fn or_empty(history: Result<Self>) -> Self {
+        history.unwrap_or_else(|e| {
+            eprintln!("warning: ignoring history: {:#}", e);
+            Self::default()
+        })
+    }
+
+    fn load(path: &Path) -> Result<Self> {
+        let mut history = Self {
+            path: Some(path.to_path_buf()),
+            entries: Vec::new(),
+        };
+
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(history),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+
+        for line in content.lines() {
+            let mut fields = line.splitn(3, '\t');
+            if let (Some(rank), Some(last_access), Some(entry_path)) =
+                (fields.next(), fields.next(), fields.next())
+            {
+                if let (Ok(rank), Ok(last_access)) = (rank.parse(), last_access.parse()) {
+                    history.entries.push(Entry {
+                        path: PathBuf::from(entry_path),
+                        rank,
+                        last_access,
+                    });
+                }
+            }
+        }
+
+        Ok(history)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let Some(ref path) = self.path else {
+            return Ok(());
+        };
+
+        let mut content = String::new();
+        for entry in &self.entries {
+            content.push_str(&format!(
+                "{}\t{}\t{}\n",
+                entry.rank,
+                entry.last_access,
+                entry.path.display()
+            ));
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+
+        let tmp = path.with_extension(format!("tmp.{}", std::process::id()));
+        fs::write(&tmp, content)
+            .and_then(|()| fs::rename(&tmp, path))
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    pub fn visit(&mut self, path: &Path, now: u64) {
+        match self.entries.iter_mut().find(|e| e.path == path) {
+            Some(entry) => {
+                entry.rank += 1.0;
+                entry.last_access = now;
+            }
+            None => self.entries.push(Entry {
+                path: path.to_path_buf(),
+                rank: 1.0,
+                last_access: now,
+            }),
+        }
+
+        let total: f64 = self.entries.iter().map(|e| e.rank).sum();
+        if total > MAX_TOTAL_RANK {
+            for entry in &mut self.entries {
+                entry.rank *= DECAY;
+            }
+            // The visit just recorded may have decayed below 1 too.
+            self.entries.retain(|e| e.rank >= 1.0 || e.path == path);
+        }
+    }
+
+    /// Drop entries whose path is gone, and with `max_age`, entries not
+    /// visited within it. Returns the removed entries.
+    pub fn prune(&mut self, max_age: Option<u64>, now: u64) -> Vec<Entry> {
+        let (kept, removed) = self.entries.drain(..).partition(|e: &Entry| {
+            e.path.exists() && max_age.is_none_or(|max| now.saturating_sub(e.last_access) <= max)
+        });
+        self.entries = kept;
+        removed
+    }
+
+    /// Order `items` by `order`; items never visited keep their relative
+    /// (alphabetical) order after the visited ones.
+    pub fn sort<T>(&self, items: &mut [T], order: SortOrder, path: impl Fn(&T) -> &Path) {
+        let now = age::now();
+        let score = |item: &T| -> f64 {
+            let entry = self.entries.iter().find(|e| e.path == path(item));
+            match (order, entry) {
+                (_, None) => 0.0,
+                (SortOrder::Recent, Some(e)) => e.last_access as f64,
+                (_, Some(e)) => e.frecency(now),
+            }
+        };
+
+        match order {
+            SortOrder::Alpha => items.sort_by(|a, b| path(a).cmp(path(b))),
+            _ => items.sort_by(|a, b| {
+                score(b)
+                    .total_cmp(&score(a))
+                    .then_with(|| path(a).cmp(path(b)))
+            }),
+        }
+    }
+}
+
+/// Record a switch to `path`. Failing to record never fails the switch.
+pub fn record(config: &Config, path: &Path) {
+    let result = History::open(config).and_then(|mut history| {
+        history.visit(path, age::now());
+        history.save()
+    });
+
+    if let Err(e) = result {
+        eprintln!("warning: failed to record history: {}", e);
+    }
+}
+
+/// `$XDG_DATA_HOME/sonic-git/history` (or `~/.local/share/sonic-git/history`).
+pub fn history_path() -> Option<PathBuf> {
+    let data_home = env::var("XDG_DATA_HOME")
+        .ok()
+        .filter(|dir| Path::new(dir).is_absolute())
+        .map(PathBuf::from)
+        .or_else(|| {
+            env::var("HOME")
+                .ok()
+                .map(|home| Path::new(&home).join(".local/share"))
+        })?;
+
+    Some(data_home.join("sonic-git").join("history"))
+}
+
+pub fn list_history(config: &Config) -> Result<()> {
+    let mut history = History::open(config)?;
+    if history.entries.is_empty() {
+        println!("No history recorded yet");
+        return Ok(());
+    }
+
+    let now = age::now();
+    let mut entries = std::mem::take(&mut history.entries);
+    entries.sort_by(|a, b| b.frecency(now).total_cmp(&a.frecency(now)));
+
+    println!("{:>8}  {:>6}  {:>6}  PATH", "SCORE", "VISITS", "LAST");
+    for entry in entries {
+        println!(
+            "{:>8.1}  {:>6.0}  {:>6}  {}",
+            entry.frecency(now),
+            entry.rank,
+            age::format_age(now.saturating_sub(entry.last_access)),
+            entry.path.display()
+        );
+    }
+
+    Ok(())
+}
+
+pub fn prune_history(config: &Config, older_than: Option<&str>) -> Result<()> {
+    let max_age = older_than.map(age::parse_age).transpose()?;
+    let mut history = History::open(config)?;
+
+    let removed = history.prune(max_age, age::now());
+    history.save()?;
+
+    for entry in &removed {
+        println!("Removed: {}", entry.path.display());
+    }
+    println!("✓ Pruned {} entries", removed.len());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    const NOW: u64 = 1_000_000_000;
+
+    #[test]
+    fn test_visit_and_round_trip() {
+        let tmp = temp_dir("history");
+        let path = tmp.join("history");
+
+        let mut history = History::load(&path).unwrap();
+        history.visit(Path::new("/src/a"), NOW);
+        history.visit(Path::new("/src/a"), NOW + 10);
+        history.visit(Path::new("/src/b c"), NOW);
+        history.save().unwrap();
+
+        let loaded = History::load(&path).unwrap();
+        assert_eq!(loaded.entries, history.entries);
+        assert_eq!(loaded.entries[0].rank, 2.0);
+        assert_eq!(loaded.entries[0].last_access, NOW + 10);
+        assert_eq!(loaded.entries[1].path, PathBuf::from("/src/b c"));
+
+        fs::remove_dir_all(&tmp).ok();
+    }
+
+    #[test]
+    fn test_record_to_configured_path() {
+        let tmp = temp_dir("history-record");
+        let config = Config {
+            history_path: Some(tmp.join("data/history")),
+            ..Default::default()
+        };
+
+        record(&config, &tmp);
+        record(&config, &tmp);
+        let history = History::open(&config).unwrap();
+        assert_eq!(history.entries.len(), 1);
+        assert_eq!(history.entries[0].path, tmp);
+        assert_eq!(history.entries[0].rank, 2.0);
+
+        // Without a path nothing is kept.
+        record(&Config::default(), &tmp);
+        assert!(History::open(&Config::default())
+            .unwrap()
+            .entries
+            .is_empty());
+
+        fs::remove_dir_all(&tmp).ok();
+    }
+
+    #[test]
+    fn test_unreadable_history_sorts_as_empty() {
+        let tmp = temp_dir("history-unreadable");
+        let path = tmp.join("history");
+        fs::write(&path, [0xff, 0xfe, b'\n']).unwrap();
+
+        assert!(History::load(&path).is_err());
+        let history = History::or_empty(History::load(&path));
+        assert!(history.entries.is_empty());
+        assert!(history.path.is_none());
+
+        fs::remove_dir_all(&tmp).ok();
+    }
+
+    #[test]
+    fn test_frecency_prefers_recent_visits() {
+        let frequent_old = Entry {
+            path: PathBuf::from("/a"),
+            rank: 10.0,
+            last_access: NOW - 30 * 24 * 3600,
+        };
+        let rare_recent = Entry {
+            path: PathBuf::from("/b"),
+            rank: 2.0,
+            last_access: NOW - 60,
+        };
+        assert!(rare_recent.frecency(NOW) > frequent_old.frecency(NOW));
+    }
+
+    #[test]
+    fn test_sort_orders() {
+        let mut history = History::default();
+        let now = age::now();
+        history.visit(Path::new("/b"), now - 100);
+        history.visit(Path::new("/b"), now - 100);
+        history.visit(Path::new("/c"), now);
+
+        let items = || {
+            vec![
+                PathBuf::from("/a"),
+                PathBuf::from("/b"),
+                PathBuf::from("/c"),
+            ]
+        };
+        let sorted = |order| {
+            let mut items = items();
+            history.sort(&mut items, order, |p| p.as_path());
+            items
+        };
+
+        assert_eq!(sorted(SortOrder::Alpha), items());
+        assert_eq!(
+            sorted(SortOrder::Recent),
+            vec![
+                PathBuf::from("/c"),
+                PathBuf::from("/b"),
+                PathBuf::from("/a")
+            ]
+        );
+        assert_eq!(
+            sorted(SortOrder::Frecent),
+            vec![
+                PathBuf::from("/b"),
+                PathBuf::from("/c"),
+                PathBuf::from("/a")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_decay_and_prune() {
+        let tmp = temp_dir("history-prune");
+        let mut history = History::default();
+        history.entries.push(Entry {
+            path: tmp.clone(),
+            rank: MAX_TOTAL_RANK,
+            last_access: NOW,
+        });
+        history.visit(&tmp.join("missing"), NOW);
+        assert!(history.entries[0].rank < MAX_TOTAL_RANK);
+        assert_eq!(history.entries.len(), 2);
+        assert_eq!(history.entries[1].path, tmp.join("missing"));
+
+        history.visit(&tmp.join("missing"), NOW);
+        history.visit(&tmp.join("missing"), NOW);
+        let removed = history.prune(None, NOW);
+        assert_eq!(removed.len(), 1);
+        assert_eq!(history.entries.len(), 1);
+
+        let removed = history.prune(Some(60), NOW + 120);
+        assert_eq!(removed.len(), 1);
+        assert!(history.entries.is_empty());
+
+        fs::remove_dir_all(&tmp).ok();
+    }
+}
@@ -0,0 +1,181 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::config::Config;
+use crate::git::git_output;
+use crate::layout::Layout;
+use crate::repo;
+use crate::repository;
+
+/// Script a repository can ship to set itself up after a clone.
+pub const REPO_HOOK: &str = ".sonic-git/post-clone";
+
+/// What a post-clone hook is told about the clone, as `G_*` environment
+/// variables.
+struct CloneInfo {
+    checkout: PathBuf,
+    repo_path: String,
+    url: String,
+    default_branch: String,
+}
+
+impl CloneInfo {
+    fn command(&self, program: &str) -> Command {
+        let mut cmd = Command::new(program);
+        cmd.current_dir(&self.checkout)
+            .env("G_REPO_DIR", &self.checkout)
+            .env("G_REPO_PATH", &self.repo_path)
+            .env("G_REPO_URL", &self.url)
+            .env("G_DEFAULT_BRANCH", &self.default_branch);
+        cmd
+    }
+}
+
+/// Run the configured post-clone hooks for the repository cloned from `url`
+/// into `repo_dir`, then its own `.sonic-git/post-clone` if trusted. Each
+/// hook is reported as a `✓`/`✗` line; a failing hook does not undo the
+/// clone.
+pub fn run_post_clone(config: &Config, repo_dir: &Path, url: &str) -> Result<()> {
+    let layout = Layout::new(config)?;
+    let info = CloneInfo {
+        checkout: repository::resolve_checkout(repo_dir)?,
+        repo_path: repo::canonical_path(config, &layout, repo_dir),
+        url: url.to_string(),
+        default_branch: git_output(repo_dir, &["symbolic-ref", "--short", "HEAD"])
+            .unwrap_or_default(),
+    };
+
+    for hook in config.post_clone_hooks_for(&info.repo_path) {
+        let mut cmd = info.command("sh");
+        cmd.args(["-c", hook]);
+        report(hook, cmd);
+    }
+
+    let script = info.checkout.join(REPO_HOOK);
+    if script.is_file() {
+        if trust(config, &info.repo_path, &script)? {
+            report(REPO_HOOK, info.command(&script.display().to_string()));
+        } else {
+            println!(
+                "! Hook: {} not run (trust it with sonic-git.hook.trust)",
+                REPO_HOOK
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn report(name: &str, mut cmd: Command) {
+    match cmd.status() {
+        Ok(status) if status.success() => println!("✓ Hook: {}", name),
+        Ok(status) => match status.code() {
+            Some(code) => println!("✗ Hook: {} (exit {})", name, code),
+            None => println!("✗ Hook: {} (killed by a signal)", name),
+        },
+        Err(e) => println!("✗ Hook: {} ({})", name, e),
+    }
+}
+
+/// Whether to run a repository's own script: when its path matches
+/// `sonic-git.hook.trust`, or when the user says so after seeing it. Without
+/// a terminal to ask on, it is not run.
+fn trust(config: &Config, repo_path: &str, script: &Path) -> Result<bool> {
+    if config.trusts_hooks_of(repo_path) {
+        return Ok(true);
+    }
+    if !io::stdin().is_terminal() {
+        return Ok(false);
+    }
+
+    let content = fs::read_to_string(script)
+        .with_context(|| format!("Failed to read {}", script.display()))?;
+    println!("{} ships {}:", repo_path, REPO_HOOK);
+    for line in content.lines() {
+        println!("  | {}", line);
+    }
+
+    print!("Run it? [y/N]: ");
+    io::stdout().flush()?;
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    Ok(input.trim().eq_ignore_ascii_case("y"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::PostCloneHook;
+    use crate::git::run_git;
+    use crate::test_support::{commit, init_repo, temp_dir};
+
+    #[test]
+    fn test_run_post_clone() {
+        let tmp = temp_dir("hooks");
+        let remote = tmp.join("remote");
+        init_repo(&remote, &["a.txt"]);
+        fs::create_dir_all(remote.join(".sonic-git")).unwrap();
+        fs::write(
+            remote.join(REPO_HOOK),
+            "#!/bin/sh\necho \"$G_REPO_PATH\" > from-repo\n",
+        )
+        .unwrap();
+        run_git(&remote, &["update-index", "--add", "--chmod=+x", REPO_HOOK]).unwrap();
+        commit(&remote, "b.txt");
+
+        let clone = tmp.join("src/example.com/org/repo");
+        run_git(
+            &tmp,
+            &[
+                "clone",
+                "--quiet",
+                remote.to_str().unwrap(),
+                clone.to_str().unwrap(),
+            ],
+        )
+        .unwrap();
+        run_git(
+            &clone,
+            &[
+                "remote",
+                "set-url",
+                "origin",
+                "https://example.com/org/repo",
+            ],
+        )
+        .unwrap();
+
+        let hook = |pattern: Option<&str>, command: &str| PostCloneHook {
+            pattern: pattern.map(str::to_string),
+            command: command.to_string(),
+        };
+        let config = Config {
+            root: Some(tmp.join("src").to_str().unwrap().to_string()),
+            post_clone_hooks: vec![
+                hook(None, "echo \"$G_DEFAULT_BRANCH $G_REPO_URL\" > global"),
+                hook(Some("example.com/org"), "touch org"),
+                hook(Some("example.com/other"), "touch other"),
+                hook(None, "exit 1"),
+            ],
+            trusted_hooks: vec!["example.com/org".to_string()],
+            ..Default::default()
+        };
+
+        run_post_clone(&config, &clone, "https://example.com/org/repo").unwrap();
+        assert_eq!(
+            fs::read_to_string(clone.join("global")).unwrap(),
+            "main https://example.com/org/repo\n"
+        );
+        assert!(clone.join("org").exists());
+        assert!(!clone.join("other").exists());
+        assert_eq!(
+            fs::read_to_string(clone.join("from-repo")).unwrap(),
+            "example.com/org/repo\n"
+        );
+
+        fs::remove_dir_all(&tmp).ok();
+    }
+}
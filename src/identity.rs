@@ -0,0 +1,186 @@
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+use crate::config::{Config, Identity};
+use crate::discovery;
+use crate::git::{git_output, run_git};
+use crate::layout::Layout;
+use crate::pool;
+use crate::repo;
+
+/// Write the identity profile matching the repository at `repo_dir` into its
+/// local config. Repositories matching no profile are left alone.
+///
+/// `repo_path` is the canonical path when known, as for `new`; otherwise it
+/// is derived from the remotes.
+pub fn apply(config: &Config, repo_dir: &Path, repo_path: Option<&str>) -> Result<()> {
+    let repo_path = match repo_path {
+        Some(repo_path) => repo_path.to_string(),
+        None => repo::canonical_path(config, &Layout::new(config)?, repo_dir),
+    };
+    let Some(identity) = config.identity_for(&repo_path) else {
+        return Ok(());
+    };
+
+    write(identity, repo_dir)?;
+
+    match identity.email {
+        Some(ref email) => println!("✓ Identity: {} ({})", identity.profile, email),
+        None => println!("✓ Identity: {}", identity.profile),
+    }
+    Ok(())
+}
+
+fn write(identity: &Identity, repo_dir: &Path) -> Result<()> {
+    for (key, value) in identity.git_config() {
+        run_git(repo_dir, &["config", "--local", key, value])?;
+    }
+    Ok(())
+}
+
+/// Settings of `identity` the repository does not use, as
+/// `(key, effective value)`. Values set globally count as long as they are
+/// the right ones.
+fn mismatches(identity: &Identity, repo_dir: &Path) -> Vec<(&'static str, Option<String>)> {
+    identity
+        .git_config()
+        .into_iter()
+        .filter_map(|(key, expected)| {
+            let actual = git_output(repo_dir, &["config", "--get", key]).ok();
+            (actual.as_deref() != Some(expected)).then_some((key, actual))
+        })
+        .collect()
+}
+
+/// Report every repository whose effective identity differs from its
+/// profile; with `fix`, write the profile into its local config.
+pub fn check(config: &Config, fix: bool) -> Result<()> {
+    if config.identities.is_empty() {
+        println!("No identity profiles configured (sonic-git.identity.<profile>.*)");
+        return Ok(());
+    }
+
+    let layout = Layout::new(config)?;
+    let repos = discovery::find_repositories(config, false)?;
+
+    let targets: Vec<(PathBuf, String, &Identity)> = repos
+        .into_iter()
+        .filter_map(|dir| {
+            let name = dir
+                .strip_prefix(&layout.root)
+                .unwrap_or(&dir)
+                .display()
+                .to_string();
+            let identity = config.identity_for(&repo::canonical_path(config, &layout, &dir))?;
+            Some((dir, name, identity))
+        })
+        .collect();
+
+    let results = pool::run(
+        &targets,
+        pool::default_jobs(),
+        |(dir, _, identity)| mismatches(identity, dir),
+        |_, _, _| {},
+    );
+
+    let mut wrong = 0;
+    for ((dir, name, identity), mismatches) in targets.iter().zip(results) {
+        if mismatches.is_empty() {
+            continue;
+        }
+        wrong += 1;
+
+        println!("{} (profile {}):", name, identity.profile);
+        for (key, actual) in mismatches {
+            println!(
+                "  {} is {}",
+                key,
+                actual.unwrap_or_else(|| "unset".to_string())
+            );
+        }
+
+        if fix {
+            write(identity, dir)?;
+            println!("  ✓ Fixed");
+        }
+    }
+
+    println!(
+        "{} repositories checked, {} with the wrong identity",
+        targets.len(),
+        wrong
+    );
+
+    if wrong > 0 && !fix {
+        anyhow::bail!(
+            "Some repositories use the wrong identity.\n\
+            Run: g sonic-repository identity check --fix"
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{init_repo, temp_dir};
+    use std::fs;
+
+    fn profile(name: &str, pattern: &str, email: &str) -> Identity {
+        Identity {
+            profile: name.to_string(),
+            patterns: vec![pattern.to_string()],
+            email: Some(email.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_identity_for_and_apply() {
+        let config = Config {
+            identities: vec![
+                profile("personal", "github.com", "me@example.com"),
+                Identity {
+                    signing_key: Some("ABC123".to_string()),
+                    ..profile("work", "github.com/acme-*", "me@acme.com")
+                },
+            ],
+            ..Default::default()
+        };
+
+        let work = config.identity_for("github.com/acme-corp/api").unwrap();
+        assert_eq!(work.profile, "work");
+        let personal = config.identity_for("github.com/poi2/g").unwrap();
+        assert_eq!(personal.profile, "personal");
+        assert!(config.identity_for("gitlab.com/acme-corp/api").is_none());
+
+        let tmp = temp_dir("identity");
+        let repo = tmp.join("repo");
+        init_repo(&repo, &["a.txt"]);
+        assert_eq!(mismatches(work, &repo).len(), 2);
+
+        run_git(
+            &repo,
+            &[
+                "remote",
+                "add",
+                "origin",
+                "git@github.com:acme-corp/api.git",
+            ],
+        )
+        .unwrap();
+        apply(&config, &repo, None).unwrap();
+        assert!(mismatches(work, &repo).is_empty());
+        assert_eq!(
+            git_output(&repo, &["config", "--local", "user.signingKey"]).unwrap(),
+            "ABC123"
+        );
+        assert_eq!(
+            mismatches(personal, &repo),
+            vec![("user.email", Some("me@acme.com".to_string()))]
+        );
+
+        fs::remove_dir_all(&tmp).ok();
+    }
+}
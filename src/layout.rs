@@ -0,0 +1,294 @@
+use anyhow::{Context, Result};
+use std::env;
+use std::path::{Component, Path, PathBuf};
+
+use crate::config::Config;
+
+pub const DEFAULT_TEMPLATE: &str = "{host}/{owner}/{repo}";
+
+/// Resolves where repositories and their worktrees live on disk.
+///
+/// Every command that maps a canonical `{host}/{path}` to a directory goes
+/// through here, so the src root and the layout template are honoured
+/// consistently.
+pub struct Layout {
+    pub root: PathBuf,
+    pub template: String,
+}
+
+impl Layout {
+    pub fn new(config: &Config) -> Result<Self> {
+        let root = match config.root {
+            Some(ref root) => PathBuf::from(root),
+            None => {
+                let home = env::var("HOME").context("HOME environment variable not set")?;
+                PathBuf::from(home).join("src")
+            }
+        };
+
+        let template = config
+            .layout_template
+            .clone()
+            .unwrap_or_else(|| DEFAULT_TEMPLATE.to_string());
+
+        Self::with_template(root, &template)
+    }
+
+    pub fn with_template(root: PathBuf, template: &str) -> Result<Self> {
+        validate_template(template)?;
+        Ok(Self {
+            root,
+            template: template.to_string(),
+        })
+    }
+
+    /// Location of a repository relative to the src root.
+    pub fn relative_path(&self, repo_path: &str) -> Result<PathBuf> {
+        render(&self.template, repo_path).map(PathBuf::from)
+    }
+
+    pub fn repo_dir(&self, repo_path: &str) -> Result<PathBuf> {
+        Ok(self.root.join(self.relative_path(repo_path)?))
+    }
+
+    pub fn worktree_base(&self) -> PathBuf {
+        if let Ok(base) = env::var("G_WORKTREE_BASE") {
+            return PathBuf::from(base);
+        }
+
+        self.root.join(".worktrees")
+    }
+
+    /// Worktrees of a repository mirror its location under the src root.
+    pub fn worktree_dir(&self, repo_path: &str) -> Result<PathBuf> {
+        Ok(self.worktree_base().join(self.relative_path(repo_path)?))
+    }
+
+    /// Shared object cache: one bare repository per remote, keyed by the
+    /// canonical path so it is independent of the layout template.
+    pub fn cache_dir(&self) -> PathBuf {
+        self.root.join(".sonic-cache")
+    }
+
+    pub fn cache_repo_dir(&self, repo_path: &str) -> PathBuf {
+        self.cache_dir().join(format!("{}.git", repo_path))
+    }
+
+    /// Deleted repositories wait here until `trash empty`.
+    pub fn trash_dir(&self) -> PathBuf {
+        self.root.join(".sonic-trash")
+    }
+
+    /// An existing directory named by its path relative to the src root, as
+    /// typed by the user. Refused when it resolves to the root itself or
+    /// anywhere outside it, through `..` or a symlink, so commands that remove
+    /// what they are given cannot reach beyond the root.
+    pub fn existing_dir(&self, relative: &str) -> Result<PathBuf> {
+        let dir = self.root.join(relative.trim_matches('/'));
+        let resolved = dir
+            .canonicalize()
+            .with_context(|| format!("Repository not found: {}", dir.display()))?;
+        let root = self
+            .root
+            .canonicalize()
+            .with_context(|| format!("Failed to resolve {}", self.root.display()))?;
+
+        if resolved == root || !resolved.starts_with(&root) {
+            anyhow::bail!(
+                "Not a repository under {}: {}",
+                self.root.display(),
+                relative
+            );
+        }
+
+        Ok(self.root.join(resolved.strip_prefix(&root)?))
+    }
+
+    /// Worktree directory for a repository that already lives at `repo_dir`,
+    /// whatever template it was placed with.
+    pub fn worktree_dir_for(&self, repo_dir: &Path) -> Option<PathBuf> {
+        repo_dir
+            .strip_prefix(&self.root)
+            .ok()
+            .map(|relative| self.worktree_base().join(relative))
+    }
+}
+
+/// Expand `{host}`, `{owner}` and `{repo}` for a canonical repository path.
+///
+/// `{owner}` takes every segment between the host and the repository name,
+/// so nested GitLab groups (`gitlab.com/a/b/c/repo`) keep their hierarchy.
+pub fn render(template: &str, repo_path: &str) -> Result<String> {
+    let segments: Vec<&str> = repo_path.trim_matches('/').split('/').collect();
+
+    // `..` would place a repository outside the src root, and an empty
+    // segment would silently shift host, owner and repo.
+    if segments.len() < 2
+        || segments
+            .iter()
+            .any(|s| s.is_empty() || *s == "." || *s == "..")
+    {
+        anyhow::bail!("Invalid repository path: {}", repo_path);
+    }
+
+    let host = segments[0];
+    let repo = segments[segments.len() - 1];
+    let owner = segments[1..segments.len() - 1].join("/");
+
+    let rendered = template
+        .replace("{host}", host)
+        .replace("{owner}", &owner)
+        .replace("{repo}", repo);
+
+    Ok(rendered
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join("/"))
+}
+
+fn validate_template(template: &str) -> Result<()> {
+    if !template.contains("{repo}") {
+        anyhow::bail!(
+            "Invalid layout template: {}\n\
+            The template must contain {{repo}}",
+            template
+        );
+    }
+
+    let stripped = template
+        .replace("{host}", "")
+        .replace("{owner}", "")
+        .replace("{repo}", "");
+    if stripped.contains('{') || stripped.contains('}') {
+        anyhow::bail!(
+            "Invalid layout template: {}\n\
+            Supported placeholders: {{host}}, {{owner}}, {{repo}}",
+            template
+        );
+    }
+
+    let path = Path::new(template);
+    if path
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_)))
+    {
+        anyhow::bail!(
+            "Invalid layout template: {}\n\
+            The template must be a relative path without '.' or '..'",
+            template
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+    use std::sync::Mutex;
+
+    static TEST_LOCK: Mutex<()> = Mutex::new(());
+
+    #[test]
+    fn test_render_default_template() {
+        let rendered = render(DEFAULT_TEMPLATE, "github.com/poi2/g").unwrap();
+        assert_eq!(rendered, "github.com/poi2/g");
+    }
+
+    #[test]
+    fn test_render_nested_groups() {
+        let rendered = render("{host}/{owner}/{repo}", "gitlab.com/a/b/c/repo").unwrap();
+        assert_eq!(rendered, "gitlab.com/a/b/c/repo");
+
+        let rendered = render("{owner}/{repo}", "gitlab.com/a/b/c/repo").unwrap();
+        assert_eq!(rendered, "a/b/c/repo");
+    }
+
+    #[test]
+    fn test_render_without_owner() {
+        let rendered = render("{host}/{owner}/{repo}", "example.com/repo").unwrap();
+        assert_eq!(rendered, "example.com/repo");
+    }
+
+    #[test]
+    fn test_render_rejects_relative_segments() {
+        assert!(render(DEFAULT_TEMPLATE, "github.com/../../etc").is_err());
+        assert!(render(DEFAULT_TEMPLATE, "github.com/org/..").is_err());
+        assert!(render(DEFAULT_TEMPLATE, "./repo").is_err());
+        assert!(render(DEFAULT_TEMPLATE, "github.com//repo").is_err());
+        assert!(render(DEFAULT_TEMPLATE, "/github.com/org/repo/").is_ok());
+    }
+
+    #[test]
+    fn test_render_custom_template() {
+        let rendered = render("{repo}@{host}", "github.com/poi2/g").unwrap();
+        assert_eq!(rendered, "g@github.com");
+    }
+
+    #[test]
+    fn test_validate_template() {
+        assert!(validate_template("{host}/{owner}/{repo}").is_ok());
+        assert!(validate_template("{host}/{owner}").is_err());
+        assert!(validate_template("{host}/{org}/{repo}").is_err());
+        assert!(validate_template("/abs/{repo}").is_err());
+        assert!(validate_template("../{repo}").is_err());
+    }
+
+    #[test]
+    fn test_layout_dirs() {
+        let _lock = TEST_LOCK.lock().unwrap();
+        env::remove_var("G_WORKTREE_BASE");
+        let layout = Layout::with_template(PathBuf::from("/src"), "{owner}/{repo}").unwrap();
+        assert_eq!(
+            layout.repo_dir("github.com/poi2/g").unwrap(),
+            PathBuf::from("/src/poi2/g")
+        );
+        assert_eq!(
+            layout.worktree_dir("github.com/poi2/g").unwrap(),
+            PathBuf::from("/src/.worktrees/poi2/g")
+        );
+    }
+
+    #[test]
+    fn test_existing_dir() {
+        let tmp = temp_dir("layout-existing");
+        let layout = Layout::with_template(tmp.join("src"), DEFAULT_TEMPLATE).unwrap();
+        std::fs::create_dir_all(tmp.join("src/github.com/org/repo")).unwrap();
+        std::fs::create_dir_all(tmp.join("outside")).unwrap();
+
+        assert_eq!(
+            layout.existing_dir("github.com/org/repo/").unwrap(),
+            tmp.join("src/github.com/org/repo")
+        );
+        assert_eq!(
+            layout.existing_dir("github.com/org/../org/repo").unwrap(),
+            tmp.join("src/github.com/org/repo")
+        );
+        assert!(layout.existing_dir("../outside").is_err());
+        assert!(layout.existing_dir("github.com/..").is_err());
+        assert!(layout.existing_dir("github.com/missing").is_err());
+
+        std::fs::remove_dir_all(&tmp).ok();
+    }
+
+    #[test]
+    fn test_get_worktree_base_default() {
+        let _lock = TEST_LOCK.lock().unwrap();
+        env::remove_var("G_WORKTREE_BASE");
+        let layout = Layout::new(&Config::default()).unwrap();
+        let base = layout.worktree_base();
+        assert!(base.to_str().unwrap().ends_with("src/.worktrees"));
+    }
+
+    #[test]
+    fn test_get_worktree_base_custom() {
+        let _lock = TEST_LOCK.lock().unwrap();
+        env::set_var("G_WORKTREE_BASE", "/custom/path");
+        let layout = Layout::new(&Config::default()).unwrap();
+        let base = layout.worktree_base();
+        assert_eq!(base, PathBuf::from("/custom/path"));
+        env::remove_var("G_WORKTREE_BASE");
+    }
+}
@@ -1,11 +1,35 @@
+mod adopt;
+mod age;
+mod archive;
 mod branch;
+mod bulk;
+mod cache;
 mod cli;
 mod config;
+mod discovery;
+mod foreach;
 mod fzf;
+mod gc;
 mod git;
+mod history;
+mod hooks;
+mod identity;
+mod layout;
+mod manifest;
 mod path;
+mod pattern;
+mod pool;
+mod relocate;
 mod repo;
 mod repository;
+mod safety;
+mod selection;
+mod shorthand;
+mod status;
+mod template;
+#[cfg(test)]
+mod test_support;
+mod trash;
 mod worktree;
 
 use anyhow::Result;
@@ -13,10 +37,7 @@ use clap::Parser;
 use cli::{Cli, Commands};
 
 fn main() -> Result<()> {
-    let config = config::Config::load().unwrap_or_else(|_| config::Config {
-        root: None,
-        aliases: std::collections::HashMap::new(),
-    });
+    let config = config::Config::load().unwrap_or_default();
 
     let mut args: Vec<String> = std::env::args().collect();
 
@@ -32,32 +53,201 @@ fn main() -> Result<()> {
         Commands::SonicRepository { cmd } => {
             use cli::RepositoryCommands;
             match cmd {
-                RepositoryCommands::Clone { url } => {
-                    git::clone_repository(&url)?;
+                RepositoryCommands::Clone {
+                    url,
+                    bare,
+                    filter,
+                    depth,
+                    branch,
+                    single_branch,
+                    no_single_branch,
+                    recurse_submodules,
+                    no_recurse_submodules,
+                    cache,
+                    no_cache,
+                    upstream,
+                    no_hooks,
+                } => {
+                    let options = config::CloneOptions {
+                        filter,
+                        depth,
+                        branch,
+                        single_branch: if no_single_branch {
+                            Some(false)
+                        } else {
+                            single_branch.then_some(true)
+                        },
+                        recurse_submodules: if no_recurse_submodules {
+                            Some(false)
+                        } else {
+                            recurse_submodules.then_some(true)
+                        },
+                        cache: if no_cache {
+                            Some(false)
+                        } else {
+                            cache.then_some(true)
+                        },
+                        ssh_command: None,
+                    };
+                    git::clone(&config, &url, bare, options, upstream.as_deref(), !no_hooks)?;
                 }
-                RepositoryCommands::Ls => {
-                    repository::list_repositories(&config)?;
+                RepositoryCommands::Ls {
+                    filters,
+                    refresh,
+                    tree,
+                } => {
+                    let selection = selection::Selection::new(&config, &filters)?;
+                    repository::list_repositories(&config, &selection, refresh, tree)?;
                 }
                 RepositoryCommands::Switch {
                     repository: repo,
                     interactive,
+                    refresh,
+                    sort,
                 } => {
-                    repository::switch_repository(&config, repo.as_deref(), interactive)?;
+                    repository::switch_repository(
+                        &config,
+                        repo.as_deref(),
+                        interactive,
+                        refresh,
+                        sort,
+                    )?;
                 }
                 RepositoryCommands::Delete {
                     repository: repo,
                     interactive,
+                    refresh,
+                    yes,
+                    force,
+                } => {
+                    repository::delete_repository(
+                        &config,
+                        repo.as_deref(),
+                        interactive,
+                        refresh,
+                        yes,
+                        force,
+                    )?;
+                }
+                RepositoryCommands::New {
+                    repository: repo,
+                    template,
+                    initial_branch,
+                    commit,
+                    origin,
                 } => {
-                    repository::delete_repository(&config, repo.as_deref(), interactive)?;
+                    let options = repository::NewOptions {
+                        template,
+                        initial_branch,
+                        commit,
+                        origin,
+                    };
+                    repository::new_repository(&config, &repo, &options)?;
+                }
+                RepositoryCommands::Migrate { template, dry_run } => {
+                    repository::migrate_repositories(&config, template.as_deref(), dry_run)?;
                 }
-                RepositoryCommands::New { repository: repo } => {
-                    repository::new_repository(&config, &repo)?;
+                RepositoryCommands::Mv {
+                    repository: repo,
+                    destination,
+                    check,
+                } => {
+                    repository::move_repository(
+                        &config,
+                        repo.as_deref(),
+                        destination.as_deref(),
+                        check,
+                    )?;
+                }
+                RepositoryCommands::Adopt {
+                    paths,
+                    scan,
+                    dry_run,
+                } => {
+                    adopt::adopt(&config, &paths, scan.as_deref(), dry_run)?;
+                }
+                RepositoryCommands::Archive {
+                    repository: repo,
+                    list,
+                    force,
+                } => match repo {
+                    Some(repo) if !list => archive::archive(&config, &repo, force)?,
+                    _ => archive::list_archives(&config)?,
+                },
+                RepositoryCommands::Unarchive { repository: repo } => {
+                    archive::unarchive(&config, &repo)?;
+                }
+                RepositoryCommands::Sync {
+                    manifest,
+                    group,
+                    no_hooks,
+                } => {
+                    manifest::sync(&config, &manifest, group.as_deref(), !no_hooks)?;
+                }
+                RepositoryCommands::Export { output } => {
+                    manifest::export(&config, output.as_deref())?;
+                }
+                RepositoryCommands::Status {
+                    filters,
+                    dirty,
+                    unpushed,
+                } => {
+                    let selection = selection::Selection::new(&config, &filters)?;
+                    status::show_status(&config, &selection, dirty, unpushed)?;
+                }
+                RepositoryCommands::Fetch { filters, jobs } => {
+                    let selection = selection::Selection::new(&config, &filters)?;
+                    let jobs = jobs.unwrap_or_else(pool::default_jobs);
+                    bulk::fetch_repositories(&config, &selection, jobs)?;
+                }
+                RepositoryCommands::Pull { filters, jobs } => {
+                    let selection = selection::Selection::new(&config, &filters)?;
+                    let jobs = jobs.unwrap_or_else(pool::default_jobs);
+                    bulk::pull_repositories(&config, &selection, jobs)?;
+                }
+                RepositoryCommands::Cache { cmd } => {
+                    use cli::CacheCommands;
+                    match cmd {
+                        CacheCommands::Add { urls } => cache::add_repositories(&config, &urls)?,
+                        CacheCommands::Refresh => cache::refresh_repositories(&config)?,
+                        CacheCommands::Gc => cache::gc_repositories(&config)?,
+                        CacheCommands::Dissociate { repository: repo } => {
+                            cache::dissociate(&config, repo.as_deref())?
+                        }
+                        CacheCommands::Ls => cache::list_repositories(&config)?,
+                    }
+                }
+                RepositoryCommands::Identity { cmd } => {
+                    use cli::IdentityCommands;
+                    match cmd {
+                        IdentityCommands::Check { fix } => identity::check(&config, fix)?,
+                    }
+                }
+                RepositoryCommands::Trash { cmd } => {
+                    use cli::TrashCommands;
+                    match cmd {
+                        TrashCommands::Ls => trash::list_trash(&config)?,
+                        TrashCommands::Restore { id } => trash::restore(&config, &id)?,
+                        TrashCommands::Empty { older_than } => {
+                            trash::empty_trash(&config, older_than.as_deref())?
+                        }
+                    }
                 }
             }
         }
         Commands::SonicWorktree { cmd } => {
             use cli::WorktreeCommands;
-            let repo_info = repo::RepoInfo::detect()?;
+            let layout = layout::Layout::new(&config)?;
+            if let WorktreeCommands::Gc {
+                all_repos: true,
+                delete,
+                yes,
+            } = cmd
+            {
+                // Works outside any repository.
+                return gc::collect_garbage(&layout, &layout.worktree_base(), delete, yes);
+            }
+            let repo_info = repo::RepoInfo::detect(&layout, config.remote_name())?;
 
             match cmd {
                 WorktreeCommands::New { branch, base } => {
@@ -72,8 +262,15 @@ fn main() -> Result<()> {
                 WorktreeCommands::Switch {
                     branch,
                     interactive,
+                    sort,
                 } => {
-                    worktree::switch_worktree(&repo_info, branch.as_deref(), interactive)?;
+                    worktree::switch_worktree(
+                        &config,
+                        &repo_info,
+                        branch.as_deref(),
+                        interactive,
+                        sort,
+                    )?;
                 }
                 WorktreeCommands::Delete {
                     branch,
@@ -89,6 +286,9 @@ fn main() -> Result<()> {
                         interactive,
                     )?;
                 }
+                WorktreeCommands::Gc { delete, yes, .. } => {
+                    gc::collect_garbage(&layout, &repo_info.worktree_base, delete, yes)?;
+                }
             }
         }
         Commands::SonicSwitch {
@@ -96,12 +296,51 @@ fn main() -> Result<()> {
             interactive,
             args,
         } => {
-            let repo_info = repo::RepoInfo::detect()?;
+            let repo_info =
+                repo::RepoInfo::detect(&layout::Layout::new(&config)?, config.remote_name())?;
             branch::switch_branch(&repo_info.repo_root, branch.as_deref(), interactive, &args)?;
         }
+        Commands::SonicForeach {
+            group,
+            all_repos,
+            worktrees,
+            jobs,
+            fail_fast,
+            keep_going: _,
+            command,
+        } => {
+            let selection = if all_repos || !group.is_empty() {
+                let groups: Vec<String> = group
+                    .iter()
+                    .map(|name| format!("@{}", name.trim_start_matches('@')))
+                    .collect();
+                Some(selection::Selection::new(&config, &groups)?)
+            } else {
+                None
+            };
+            let jobs = jobs.unwrap_or_else(pool::default_jobs);
+            foreach::foreach(
+                &config,
+                selection.as_ref(),
+                worktrees,
+                jobs,
+                fail_fast,
+                &command,
+            )?;
+        }
+        Commands::SonicHistory { cmd } => {
+            use cli::HistoryCommands;
+            match cmd {
+                HistoryCommands::Ls => history::list_history(&config)?,
+                HistoryCommands::Prune { older_than } => {
+                    history::prune_history(&config, older_than.as_deref())?
+                }
+            }
+        }
         Commands::SonicBranch { cmd } => {
             use cli::BranchCommands;
-            let repo_info = repo::RepoInfo::detect()?;
+            let repo_info =
+                repo::RepoInfo::detect(&layout::Layout::new(&config)?, config.remote_name())?;
 
             match cmd {
                 BranchCommands::Ls { options } => {
@@ -0,0 +1,318 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::{CloneOptions, Config};
+use crate::discovery;
+use crate::git::{self, run_git};
+use crate::layout::Layout;
+use crate::path;
+use crate::repo;
+use crate::repository;
+use crate::shorthand;
+
+/// A declarative list of repositories, read from and written to TOML:
+///
+/// ```toml
+/// [[repo]]
+/// url = "git@github.com:poi2/g.git"
+/// groups = ["tools"]
+/// bare = true
+/// filter = "blob:none"
+/// ```
+#[derive(Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    #[serde(default, rename = "repo")]
+    pub repos: Vec<ManifestRepo>,
+}
+
+/// One manifest entry. Clone options left unset fall back to the per-host
+/// and global `sonic-git.clone.*` defaults, as with `clone`.
+#[derive(Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ManifestRepo {
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub bare: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filter: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub depth: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub single_branch: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recurse_submodules: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache: Option<bool>,
+}
+
+impl ManifestRepo {
+    fn clone_options(&self) -> CloneOptions {
+        CloneOptions {
+            filter: self.filter.clone(),
+            depth: self.depth,
+            branch: self.branch.clone(),
+            single_branch: self.single_branch,
+            recurse_submodules: self.recurse_submodules,
+            cache: self.cache,
+            ssh_command: None,
+        }
+    }
+}
+
+impl Manifest {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read manifest: {}", path.display()))?;
+        toml::from_str(&content)
+            .with_context(|| format!("Failed to parse manifest: {}", path.display()))
+    }
+}
+
+#[derive(Debug, Default)]
+struct SyncReport {
+    cloned: Vec<String>,
+    fetched: Vec<String>,
+    failed: Vec<(String, String)>,
+    unmanaged: Vec<PathBuf>,
+}
+
+/// Clone every manifest entry that is missing, fetch the ones already on
+/// disk, and report repositories under the src root the manifest does not
+/// list.
+pub fn sync(config: &Config, manifest_path: &str, group: Option<&str>, hooks: bool) -> Result<()> {
+    let manifest = Manifest::load(Path::new(manifest_path))?;
+    let report = sync_manifest(config, &manifest, group, hooks)?;
+
+    let src_root = repository::get_src_root(config)?;
+
+    println!();
+    println!(
+        "Cloned: {}, fetched: {}, failed: {}",
+        report.cloned.len(),
+        report.fetched.len(),
+        report.failed.len()
+    );
+
+    if !report.unmanaged.is_empty() {
+        println!();
+        println!("Not in manifest:");
+        for dir in &report.unmanaged {
+            let relative = dir.strip_prefix(&src_root).unwrap_or(dir);
+            println!("  {}", relative.display());
+        }
+    }
+
+    if !report.failed.is_empty() {
+        println!();
+        for (url, error) in &report.failed {
+            println!("✗ {}: {}", url, error);
+        }
+        anyhow::bail!("Failed to sync {} repositories", report.failed.len());
+    }
+
+    Ok(())
+}
+
+fn sync_manifest(
+    config: &Config,
+    manifest: &Manifest,
+    group: Option<&str>,
+    hooks: bool,
+) -> Result<SyncReport> {
+    let layout = Layout::new(config)?;
+    let mut report = SyncReport::default();
+    let mut managed = HashSet::new();
+
+    for entry in &manifest.repos {
+        let target = shorthand::resolve_clone_url(&entry.url, config).and_then(|url| {
+            let repo_dir = layout.repo_dir(&path::parse_repo_path(&url)?)?;
+            Ok((url, repo_dir))
+        });
+
+        let (url, repo_dir) = match target {
+            Ok(target) => target,
+            Err(e) => {
+                report.failed.push((entry.url.clone(), e.to_string()));
+                continue;
+            }
+        };
+        managed.insert(repo_dir.clone());
+
+        if group.is_some_and(|group| !entry.groups.iter().any(|g| g == group)) {
+            continue;
+        }
+
+        let result = if repo_dir.exists() {
+            println!("Fetching {}...", url);
+            run_git(
+                &repo_dir,
+                &["fetch", "--quiet", "--prune", config.remote_name()],
+            )
+            .map(|()| report.fetched.push(url.clone()))
+        } else {
+            git::clone(config, &url, entry.bare, entry.clone_options(), None, hooks)
+                .map(|()| report.cloned.push(url.clone()))
+        };
+
+        if let Err(e) = result {
+            report.failed.push((url, e.to_string()));
+        }
+    }
+
+    report.unmanaged = discovery::find_repositories(config, false)?
+        .into_iter()
+        .filter(|dir| !managed.contains(dir))
+        .collect();
+
+    Ok(report)
+}
+
+/// Write every repository under the src root as a manifest, to `output` or
+/// stdout.
+pub fn export(config: &Config, output: Option<&str>) -> Result<()> {
+    let manifest = collect_manifest(config)?;
+    let content = toml::to_string_pretty(&manifest).context("Failed to serialize manifest")?;
+
+    match output {
+        Some(output) => {
+            fs::write(output, content)
+                .with_context(|| format!("Failed to write manifest: {}", output))?;
+            println!(
+                "✓ Exported {} repositories to {}",
+                manifest.repos.len(),
+                output
+            );
+        }
+        None => print!("{}", content),
+    }
+
+    Ok(())
+}
+
+fn collect_manifest(config: &Config) -> Result<Manifest> {
+    let mut manifest = Manifest::default();
+
+    for repo_dir in discovery::find_repositories(config, false)? {
+        match repo::get_remote_url(&repo_dir, config.remote_name()) {
+            Ok(url) => manifest.repos.push(ManifestRepo {
+                url,
+                bare: repo::is_bare_repository(&repo_dir),
+                ..Default::default()
+            }),
+            Err(_) => eprintln!(
+                "warning: skipping repository without origin: {}",
+                repo_dir.display()
+            ),
+        }
+    }
+
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{commit, init_repo, temp_dir};
+
+    #[test]
+    fn test_parse_manifest() {
+        let manifest: Manifest = toml::from_str(
+            r#"
+            [[repo]]
+            url = "gh:poi2/g"
+            groups = ["tools"]
+            bare = true
+            depth = 1
+            single-branch = true
+
+            [[repo]]
+            url = "https://gitlab.com/team/app.git"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(manifest.repos.len(), 2);
+        assert_eq!(manifest.repos[0].groups, vec!["tools"]);
+        assert!(manifest.repos[0].bare);
+
+        let options = manifest.repos[0].clone_options();
+        assert_eq!(options.depth, Some(1));
+        assert_eq!(options.single_branch, Some(true));
+        assert_eq!(manifest.repos[1].clone_options().depth, None);
+
+        assert!(toml::from_str::<Manifest>("[[repo]]\nurl = \"a/b\"\nshallow = 1\n").is_err());
+    }
+
+    #[test]
+    fn test_sync_and_export() {
+        let tmp = temp_dir("manifest");
+        let remote_a = tmp.join("remotes/a");
+        let remote_b = tmp.join("remotes/b");
+        init_repo(&remote_a, &["a.txt"]);
+        init_repo(&remote_b, &["b.txt"]);
+
+        let config = Config {
+            root: Some(tmp.join("src").to_string_lossy().into_owned()),
+            ..Default::default()
+        };
+        let layout = Layout::new(&config).unwrap();
+
+        let url_a = format!("file://{}", remote_a.display());
+        let url_b = format!("file://{}", remote_b.display());
+        let manifest = Manifest {
+            repos: vec![
+                ManifestRepo {
+                    url: url_a.clone(),
+                    groups: vec!["work".to_string()],
+                    ..Default::default()
+                },
+                ManifestRepo {
+                    url: url_b.clone(),
+                    bare: true,
+                    ..Default::default()
+                },
+            ],
+        };
+
+        // A group filter only touches matching entries.
+        let report = sync_manifest(&config, &manifest, Some("work"), true).unwrap();
+        assert_eq!(report.cloned, vec![url_a.clone()]);
+        assert!(report.unmanaged.is_empty());
+
+        commit(&remote_a, "c.txt");
+        let report = sync_manifest(&config, &manifest, None, true).unwrap();
+        assert_eq!(report.cloned, vec![url_b.clone()]);
+        assert_eq!(report.fetched, vec![url_a.clone()]);
+        assert!(report.failed.is_empty());
+
+        let repo_a = layout
+            .repo_dir(&path::parse_repo_path(&url_a).unwrap())
+            .unwrap();
+        let log = git::git_output(&repo_a, &["log", "--oneline", "origin/main"]).unwrap();
+        assert_eq!(log.lines().count(), 2);
+
+        let partial = Manifest {
+            repos: vec![ManifestRepo {
+                url: url_b.clone(),
+                ..Default::default()
+            }],
+        };
+        let report = sync_manifest(&config, &partial, None, true).unwrap();
+        assert_eq!(report.unmanaged, vec![repo_a.clone()]);
+
+        let exported = collect_manifest(&config).unwrap();
+        let mut expected = manifest;
+        expected.repos[0].groups.clear();
+        assert_eq!(exported, expected);
+
+        fs::remove_dir_all(&tmp).ok();
+    }
+}
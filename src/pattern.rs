@@ -0,0 +1,104 @@
+/// Whether `pattern` selects the repository at canonical path `repo_path`
+/// (`{host}/{path}`).
+///
+/// Patterns are matched segment by segment and select everything below
+/// them, so `github.com` matches every GitHub repository and
+/// `github.com/poi2` every repository of that owner. Within a segment `*`
+/// matches any run of characters (`github.com/*-infra`), and a `**` segment
+/// matches any number of segments (`**/dotfiles`).
+pub fn matches(pattern: &str, repo_path: &str) -> bool {
+    let pattern: Vec<&str> = segments(pattern).collect();
+    let path: Vec<&str> = segments(repo_path).collect();
+    !pattern.is_empty() && match_segments(&pattern, &path)
+}
+
+/// How specific a matching pattern is, to pick the most specific of several
+/// matching patterns: segments without wildcards first, then segments other
+/// than `**`, then literal characters. `github.com/acme` beats both
+/// `github.com/*` and `**/a-very-long-repository-name`.
+pub fn specificity(pattern: &str) -> (usize, usize, usize) {
+    let segments: Vec<&str> = segments(pattern).collect();
+    (
+        segments.iter().filter(|s| !s.contains('*')).count(),
+        segments.iter().filter(|s| **s != "**").count(),
+        pattern.chars().filter(|c| *c != '*' && *c != '/').count(),
+    )
+}
+
+fn segments(value: &str) -> impl Iterator<Item = &str> {
+    value.split('/').filter(|s| !s.is_empty())
+}
+
+fn match_segments(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        // The pattern is used up: whatever is left lies below it.
+        None => true,
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| match_segments(rest, &path[skip..])),
+        Some((first, rest)) => match path.split_first() {
+            Some((segment, path_rest)) => {
+                match_segment(first, segment) && match_segments(rest, path_rest)
+            }
+            None => false,
+        },
+    }
+}
+
+fn match_segment(pattern: &str, segment: &str) -> bool {
+    let pattern = pattern.to_ascii_lowercase();
+    let segment = segment.to_ascii_lowercase();
+
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    let Some(mut rest) = segment.strip_prefix(first) else {
+        return false;
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // No `*`: the whole segment must match.
+        return rest.is_empty();
+    };
+
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+
+    rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        assert!(matches("github.com", "github.com/poi2/g"));
+        assert!(matches("github.com/poi2", "github.com/poi2/g"));
+        assert!(matches("github.com/poi2/g", "github.com/poi2/g"));
+        assert!(matches("GitHub.com/poi2/", "github.com/poi2/g"));
+        assert!(!matches("github.com/poi", "github.com/poi2/g"));
+        assert!(!matches("github.com/poi2/g/x", "github.com/poi2/g"));
+        assert!(!matches("", "github.com/poi2/g"));
+
+        assert!(matches("*.example.com", "git.example.com/team/app"));
+        assert!(matches("github.com/*-infra", "github.com/acme-infra/tf"));
+        assert!(matches("github.com/a*b*c", "github.com/abxbc/repo"));
+        assert!(!matches("github.com/a*b*c", "github.com/abxbcd/repo"));
+        assert!(matches("github.com/*/g", "github.com/poi2/g"));
+
+        assert!(matches("**/dotfiles", "github.com/poi2/dotfiles"));
+        assert!(matches("gitlab.com/**/infra", "gitlab.com/a/b/c/infra"));
+        assert!(!matches("**/dotfiles", "github.com/poi2/g"));
+    }
+
+    #[test]
+    fn test_specificity() {
+        assert!(specificity("github.com/acme") > specificity("github.com"));
+        assert!(specificity("github.com/acme") > specificity("github.com/*"));
+        assert!(specificity("github.com/acme") > specificity("**/a-very-long-repository-name"));
+        assert!(specificity("github.com/*-infra") > specificity("github.com"));
+    }
+}
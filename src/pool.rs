@@ -0,0 +1,80 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+/// Default number of workers: one per available CPU.
+pub fn default_jobs() -> usize {
+    thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(4)
+}
+
+/// Run `work` over `items` on at most `jobs` threads.
+///
+/// `report` is called on the calling thread as each item finishes, in
+/// completion order, with the number of items done so far. The results are
+/// returned in input order.
+pub fn run<T, R, W, P>(items: &[T], jobs: usize, work: W, mut report: P) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    W: Fn(&T) -> R + Sync,
+    P: FnMut(usize, &T, &R),
+{
+    let next = AtomicUsize::new(0);
+    let (tx, rx) = mpsc::channel();
+    let mut results: Vec<Option<R>> = items.iter().map(|_| None).collect();
+
+    thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, items.len().max(1)) {
+            let tx = tx.clone();
+            let next = &next;
+            let work = &work;
+            scope.spawn(move || loop {
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                if index >= items.len() {
+                    break;
+                }
+                if tx.send((index, work(&items[index]))).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(tx);
+
+        for (done, (index, result)) in rx.iter().enumerate() {
+            report(done + 1, &items[index], &result);
+            results[index] = Some(result);
+        }
+    });
+
+    results.into_iter().map(|r| r.unwrap()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_keeps_input_order() {
+        let items: Vec<u64> = (0..20).collect();
+        let mut reported = 0;
+
+        let results = run(
+            &items,
+            4,
+            |n| {
+                thread::sleep(std::time::Duration::from_millis(20 - n));
+                n * 2
+            },
+            |done, _, _| {
+                reported += 1;
+                assert_eq!(done, reported);
+            },
+        );
+
+        assert_eq!(results, (0..20).map(|n| n * 2).collect::<Vec<_>>());
+        assert_eq!(reported, 20);
+        assert!(run(&Vec::<u64>::new(), 4, |n| *n, |_, _, _| {}).is_empty());
+    }
+}
@@ -0,0 +1,195 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::worktree::Worktree;
+
+/// Move a repository (working copy or bare hub) from `from` to `to`, along
+/// with the directory holding its worktrees, and re-link every worktree.
+///
+/// Moving either side breaks the `.git` files and `gitdir` back-links that
+/// tie a linked worktree to its repository; `git worktree repair` is given
+/// the new worktree paths so both directions are fixed.
+pub fn relocate(from: &Path, to: &Path, worktrees_from: &Path, worktrees_to: &Path) -> Result<()> {
+    if to.exists() {
+        anyhow::bail!("Destination already exists: {}", to.display());
+    }
+
+    let move_worktrees = worktrees_from != worktrees_to && worktrees_from.exists();
+    if move_worktrees && worktrees_to.exists() {
+        anyhow::bail!(
+            "Worktree destination already exists: {}",
+            worktrees_to.display()
+        );
+    }
+
+    let linked: Vec<PathBuf> = Worktree::list(&from.to_path_buf())?
+        .into_iter()
+        .skip(1)
+        .map(|wt| wt.path)
+        .collect();
+
+    move_dir(from, to)?;
+
+    if move_worktrees {
+        if let Err(e) = move_dir(worktrees_from, worktrees_to) {
+            move_dir(to, from).ok();
+            return Err(e);
+        }
+    }
+
+    let relinked: Vec<PathBuf> = linked
+        .iter()
+        .map(|path| {
+            if let Ok(rest) = path.strip_prefix(from) {
+                to.join(rest)
+            } else if let (true, Ok(rest)) = (move_worktrees, path.strip_prefix(worktrees_from)) {
+                worktrees_to.join(rest)
+            } else {
+                path.clone()
+            }
+        })
+        .filter(|path| path.exists())
+        .collect();
+
+    repair_worktrees(to, &relinked)?;
+
+    Ok(())
+}
+
+pub fn repair_worktrees(repo_dir: &Path, worktrees: &[PathBuf]) -> Result<()> {
+    let output = Command::new("git")
+        .args(["worktree", "repair"])
+        .args(worktrees)
+        .current_dir(repo_dir)
+        .output()
+        .context("Failed to execute git worktree repair")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("git worktree repair failed: {}", stderr.trim());
+    }
+
+    Ok(())
+}
+
+/// Move a directory, copying it and removing the original when `to` is on
+/// another filesystem (e.g. a `G_WORKTREE_BASE` on a separate disk).
+pub fn move_dir(from: &Path, to: &Path) -> Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+    }
+
+    match fs::rename(from, to) {
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            if let Err(e) = copy_tree(from, to) {
+                fs::remove_dir_all(to).ok();
+                return Err(e);
+            }
+            fs::remove_dir_all(from)
+                .with_context(|| format!("Failed to remove {}", from.display()))?;
+        }
+        result => result
+            .with_context(|| format!("Failed to move {} to {}", from.display(), to.display()))?,
+    }
+
+    Ok(())
+}
+
+/// Copy a directory tree, keeping permissions and symlinks as they are.
+fn copy_tree(from: &Path, to: &Path) -> Result<()> {
+    fs::create_dir(to).with_context(|| format!("Failed to create {}", to.display()))?;
+    fs::set_permissions(to, fs::metadata(from)?.permissions())?;
+
+    let entries =
+        fs::read_dir(from).with_context(|| format!("Failed to read {}", from.display()))?;
+    for entry in entries {
+        let entry = entry?;
+        let (source, target) = (entry.path(), to.join(entry.file_name()));
+        let file_type = entry.file_type()?;
+
+        if file_type.is_dir() {
+            copy_tree(&source, &target)?;
+        } else if file_type.is_symlink() {
+            copy_symlink(&source, &target)?;
+        } else {
+            fs::copy(&source, &target)
+                .with_context(|| format!("Failed to copy {}", source.display()))?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn copy_symlink(source: &Path, target: &Path) -> Result<()> {
+    std::os::unix::fs::symlink(fs::read_link(source)?, target)
+        .with_context(|| format!("Failed to copy {}", source.display()))
+}
+
+#[cfg(not(unix))]
+fn copy_symlink(source: &Path, _target: &Path) -> Result<()> {
+    anyhow::bail!("Cannot copy symlink {} across devices", source.display())
+}
+
+/// Remove directories left empty by a move, walking up from `path` but never
+/// past `stop`.
+pub fn remove_empty_parents(path: &Path, stop: &Path) {
+    let mut current = path.parent();
+
+    while let Some(dir) = current {
+        if dir == stop || !dir.starts_with(stop) || fs::remove_dir(dir).is_err() {
+            break;
+        }
+        current = dir.parent();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_remove_empty_parents() {
+        let root = env::temp_dir().join(format!("sonic-git-empty-parents-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let keep = root.join("github.com/keep");
+        let gone = root.join("gitlab.com/group/sub/repo");
+        fs::create_dir_all(&keep).unwrap();
+        fs::create_dir_all(&gone).unwrap();
+        fs::remove_dir(&gone).unwrap();
+
+        remove_empty_parents(&gone, &root);
+
+        assert!(!root.join("gitlab.com").exists());
+        assert!(keep.exists());
+        assert!(root.exists());
+        fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn test_copy_tree() {
+        let root = env::temp_dir().join(format!("sonic-git-copy-tree-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let from = root.join("from");
+        fs::create_dir_all(from.join("sub")).unwrap();
+        fs::write(from.join("sub/file"), "content").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink("sub/file", from.join("link")).unwrap();
+
+        let to = root.join("to");
+        copy_tree(&from, &to).unwrap();
+
+        assert_eq!(fs::read_to_string(to.join("sub/file")).unwrap(), "content");
+        #[cfg(unix)]
+        assert_eq!(
+            fs::read_link(to.join("link")).unwrap(),
+            Path::new("sub/file")
+        );
+        fs::remove_dir_all(&root).ok();
+    }
+}
@@ -1,36 +1,97 @@
 use anyhow::{Context, Result};
-use std::env;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
+use crate::config::{Config, ForkPlacement};
+use crate::git::git_output;
+use crate::layout::Layout;
 use crate::path;
+use crate::worktree::Worktree;
+
+/// Remote a fork's original repository is added as.
+pub const UPSTREAM_REMOTE: &str = "upstream";
 
 pub struct RepoInfo {
     #[allow(dead_code)]
     pub repo_root: PathBuf,
+    /// `None` for repositories without the configured remote.
     #[allow(dead_code)]
-    pub remote_url: String,
+    pub remote_url: Option<String>,
+    /// The repository this one is a fork of, when it has an `upstream` remote.
+    pub upstream_url: Option<String>,
     #[allow(dead_code)]
     pub repo_path: String,
     pub main_repo_dir: PathBuf,
     pub worktree_base: PathBuf,
+    pub is_bare: bool,
 }
 
 impl RepoInfo {
-    pub fn detect() -> Result<Self> {
+    /// Identify the repository of the current directory. The main working
+    /// tree comes from git itself, so this works for a repository anywhere
+    /// on disk; the src root layout only decides where new worktrees go.
+    pub fn detect(layout: &Layout, remote: &str) -> Result<Self> {
         let repo_root = Self::find_git_root()?;
-        let remote_url = Self::get_remote_url(&repo_root)?;
+        Self::open(layout, repo_root, remote)
+    }
+
+    /// Information for a freshly cloned repository at `repo_root`. That is
+    /// not always where `remote_url` maps to: a fork may be placed at its
+    /// upstream's path, and its worktrees go next to where it actually is.
+    pub fn new(layout: &Layout, repo_root: PathBuf, remote_url: String) -> Result<Self> {
         let repo_path = path::parse_repo_path(&remote_url)?;
 
-        let home = env::var("HOME").context("HOME environment variable not set")?;
-        let main_repo_dir = PathBuf::from(&home).join("src").join(&repo_path);
+        let worktree_base = match layout.worktree_dir_for(&repo_root) {
+            Some(dir) => dir,
+            None => layout.worktree_dir(&repo_path)?,
+        };
 
-        let worktree_base = Self::get_worktree_base()?.join(&repo_path);
+        Ok(Self {
+            upstream_url: find_remote_url(&repo_root, UPSTREAM_REMOTE),
+            is_bare: is_bare_repository(&repo_root),
+            main_repo_dir: repo_root.clone(),
+            repo_root,
+            remote_url: Some(remote_url),
+            repo_path,
+            worktree_base,
+        })
+    }
+
+    /// `repo_root` is any checkout of the repository: the main working tree,
+    /// a linked worktree or a bare hub. `remote` (`sonic-git.remote`, default
+    /// `origin`) names the repository when it exists.
+    fn open(layout: &Layout, repo_root: PathBuf, remote: &str) -> Result<Self> {
+        let main_repo_dir = find_main_repo_dir(&repo_root)?;
+        let remote_url = find_remote_url(&repo_root, remote);
+
+        // Without a (parsable) remote, a repository is named by its path
+        // under the src root, or as `localhost/<absolute path>` outside it,
+        // like a `file://` clone.
+        let relative = main_repo_dir
+            .strip_prefix(&layout.root)
+            .ok()
+            .filter(|relative| relative.components().count() > 0);
+        let repo_path = match remote_url.as_deref().map(path::parse_repo_path) {
+            Some(Ok(repo_path)) => repo_path,
+            _ => match relative {
+                Some(relative) => relative.display().to_string(),
+                None => path::parse_repo_path(&main_repo_dir.display().to_string())?,
+            },
+        };
+
+        // Worktrees mirror where the repository actually is under the src
+        // root; a repository elsewhere gets the place it would be cloned to.
+        let worktree_base = match relative {
+            Some(relative) => layout.worktree_base().join(relative),
+            None => layout.worktree_dir(&repo_path)?,
+        };
 
         Ok(Self {
+            upstream_url: find_remote_url(&repo_root, UPSTREAM_REMOTE),
             repo_root,
             remote_url,
             repo_path,
+            is_bare: is_bare_repository(&main_repo_dir),
             main_repo_dir,
             worktree_base,
         })
@@ -43,6 +104,10 @@ impl RepoInfo {
             .context("Failed to execute git rev-parse")?;
 
         if !output.status.success() {
+            if let Some(hub) = Self::find_bare_hub()? {
+                return Ok(hub);
+            }
+
             anyhow::bail!(
                 "Not in a git repository.\n\
                 Run this command from within a git repository."
@@ -57,55 +122,309 @@ impl RepoInfo {
         Ok(PathBuf::from(path))
     }
 
-    fn get_remote_url(repo_root: &PathBuf) -> Result<String> {
+    /// Inside a bare hub there is no work tree, so `--show-toplevel` fails.
+    /// Fall back to the directory holding the bare `.git`.
+    fn find_bare_hub() -> Result<Option<PathBuf>> {
         let output = Command::new("git")
-            .args(["remote", "get-url", "origin"])
-            .current_dir(repo_root)
+            .args(["rev-parse", "--is-bare-repository", "--absolute-git-dir"])
             .output()
-            .context("Failed to get remote URL")?;
+            .context("Failed to execute git rev-parse")?;
 
         if !output.status.success() {
-            anyhow::bail!(
-                "No remote 'origin' found.\n\
-                Please add a remote:\n  \
-                git remote add origin <url>"
-            );
+            return Ok(None);
+        }
+
+        let stdout = String::from_utf8(output.stdout)?;
+        let mut lines = stdout.lines();
+        if lines.next() != Some("true") {
+            return Ok(None);
         }
 
-        Ok(String::from_utf8(output.stdout)?.trim().to_string())
+        let git_dir = match lines.next() {
+            Some(dir) => PathBuf::from(dir),
+            None => return Ok(None),
+        };
+
+        Ok(Some(hub_dir(&git_dir)))
+    }
+}
+
+/// The main working tree of the repository `dir` belongs to, or the hub
+/// directory of a bare repository. `dir` is the top of a checkout or a hub.
+pub fn find_main_repo_dir(dir: &Path) -> Result<PathBuf> {
+    let output = git_output(
+        dir,
+        &[
+            "rev-parse",
+            "--path-format=absolute",
+            "--git-dir",
+            "--git-common-dir",
+        ],
+    )?;
+    let (git_dir, common_dir) = output
+        .split_once('\n')
+        .context("Unexpected git rev-parse output")?;
+
+    // Only the main worktree uses the common dir as its own git dir. This
+    // also covers a git dir kept elsewhere (`--separate-git-dir`).
+    if git_dir == common_dir {
+        return Ok(dir.to_path_buf());
+    }
+
+    // From a linked worktree, the first one listed is the main one.
+    let common_dir = PathBuf::from(common_dir);
+    match Worktree::list(&common_dir)?.into_iter().next() {
+        Some(main) if !main.is_bare => Ok(main.path),
+        _ => Ok(hub_dir(&common_dir)),
     }
+}
 
-    pub fn get_worktree_base() -> Result<PathBuf> {
-        if let Ok(base) = env::var("G_WORKTREE_BASE") {
-            return Ok(PathBuf::from(base));
+/// The URL that decides where a repository belongs: a fork's `upstream`
+/// when forks are placed at their upstream's path, otherwise `remote`
+/// (`sonic-git.remote`, default `origin`).
+pub fn placement_url(repo_root: &Path, placement: ForkPlacement, remote: &str) -> Result<String> {
+    if placement == ForkPlacement::Upstream {
+        if let Some(url) = find_remote_url(repo_root, UPSTREAM_REMOTE) {
+            return Ok(url);
         }
+    }
+    get_remote_url(repo_root, remote)
+}
 
-        let home = env::var("HOME").context("HOME environment variable not set")?;
-        Ok(PathBuf::from(home).join("src/.worktrees"))
+/// The canonical `{host}/{path}` of the repository at `repo_dir`, as
+/// identity profiles and repository patterns see it: that of the remote it is
+/// placed by, or its path under the src root without one.
+pub fn canonical_path(config: &Config, layout: &Layout, repo_dir: &Path) -> String {
+    placement_url(repo_dir, config.fork_placement, config.remote_name())
+        .and_then(|url| path::parse_repo_path(&url))
+        .unwrap_or_else(|_| {
+            repo_dir
+                .strip_prefix(&layout.root)
+                .unwrap_or(repo_dir)
+                .display()
+                .to_string()
+        })
+}
+
+pub fn get_remote_url(repo_root: &Path, remote: &str) -> Result<String> {
+    find_remote_url(repo_root, remote).ok_or_else(|| {
+        anyhow::anyhow!(
+            "No remote '{}' found.\n\
+            Please add a remote:\n  \
+            git remote add {} <url>",
+            remote,
+            remote
+        )
+    })
+}
+
+pub fn find_remote_url(repo_root: &Path, remote: &str) -> Option<String> {
+    git_output(repo_root, &["remote", "get-url", remote])
+        .ok()
+        .filter(|url| !url.is_empty())
+}
+
+/// The directory a bare hub lives in: `repo/.git` maps to `repo`, while a
+/// plain `repo.git` is its own hub.
+pub fn hub_dir(git_dir: &Path) -> PathBuf {
+    if git_dir.file_name().is_some_and(|name| name == ".git") {
+        if let Some(parent) = git_dir.parent() {
+            return parent.to_path_buf();
+        }
     }
+    git_dir.to_path_buf()
+}
+
+pub fn is_bare_repository(dir: &Path) -> bool {
+    if !dir.exists() {
+        return false;
+    }
+
+    Command::new("git")
+        .args(["rev-parse", "--is-bare-repository"])
+        .current_dir(dir)
+        .output()
+        .map(|output| output.status.success() && output.stdout.trim_ascii() == b"true")
+        .unwrap_or(false)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::sync::Mutex;
+    use crate::git::run_git;
+    use crate::layout::DEFAULT_TEMPLATE;
+    use crate::test_support::{init_repo, temp_dir};
+
+    #[test]
+    fn test_hub_dir() {
+        assert_eq!(
+            hub_dir(Path::new("/src/github.com/poi2/g/.git")),
+            PathBuf::from("/src/github.com/poi2/g")
+        );
+        assert_eq!(
+            hub_dir(Path::new("/srv/git/g.git")),
+            PathBuf::from("/srv/git/g.git")
+        );
+    }
 
-    static TEST_LOCK: Mutex<()> = Mutex::new(());
+    #[test]
+    fn test_canonical_path_uses_configured_remote() {
+        let tmp = temp_dir("repo-remote");
+        let layout = Layout::with_template(tmp.join("src"), DEFAULT_TEMPLATE).unwrap();
+        let repo = tmp.join("src/example.com/me/project");
+        init_repo(&repo, &["a.txt"]);
+        run_git(
+            &repo,
+            &["remote", "add", "home", "https://github.com/org/project"],
+        )
+        .unwrap();
+
+        let config = Config {
+            remote: Some("home".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            canonical_path(&config, &layout, &repo),
+            "github.com/org/project"
+        );
+        assert_eq!(
+            canonical_path(&Config::default(), &layout, &repo),
+            "example.com/me/project"
+        );
+        assert!(get_remote_url(&repo, "origin")
+            .unwrap_err()
+            .to_string()
+            .contains("No remote 'origin'"));
+
+        std::fs::remove_dir_all(&tmp).ok();
+    }
 
     #[test]
-    fn test_get_worktree_base_default() {
-        let _lock = TEST_LOCK.lock().unwrap();
-        env::remove_var("G_WORKTREE_BASE");
-        let base = RepoInfo::get_worktree_base().unwrap();
-        assert!(base.to_str().unwrap().ends_with("src/.worktrees"));
+    fn test_open_without_remote() {
+        let tmp = temp_dir("repo-location");
+        let layout = Layout::with_template(tmp.join("src"), DEFAULT_TEMPLATE).unwrap();
+
+        let repo = tmp.join("src/example.com/me/project");
+        init_repo(&repo, &["a.txt"]);
+        let worktree = tmp.join("feature");
+        run_git(
+            &repo,
+            &[
+                "worktree",
+                "add",
+                "--quiet",
+                "-b",
+                "feature",
+                worktree.to_str().unwrap(),
+            ],
+        )
+        .unwrap();
+        assert_eq!(find_remote_url(&repo, "origin"), None);
+
+        let info = RepoInfo::open(&layout, worktree, "origin").unwrap();
+        assert_eq!(info.main_repo_dir, repo);
+        assert_eq!(info.repo_path, "example.com/me/project");
+        assert_eq!(
+            info.worktree_base,
+            layout.worktree_base().join("example.com/me/project")
+        );
+        assert!(info.remote_url.is_none());
+
+        let outside = tmp.join("scratch/tool");
+        init_repo(&outside, &["a.txt"]);
+        let info = RepoInfo::open(&layout, outside.clone(), "origin").unwrap();
+        let repo_path = format!("localhost{}", outside.display());
+        assert_eq!(info.main_repo_dir, outside);
+        assert_eq!(info.worktree_base, layout.worktree_dir(&repo_path).unwrap());
+        assert_eq!(info.repo_path, repo_path);
+
+        std::fs::remove_dir_all(&tmp).ok();
     }
 
     #[test]
-    fn test_get_worktree_base_custom() {
-        let _lock = TEST_LOCK.lock().unwrap();
-        env::set_var("G_WORKTREE_BASE", "/custom/path");
-        let base = RepoInfo::get_worktree_base().unwrap();
-        assert_eq!(base, PathBuf::from("/custom/path"));
-        env::remove_var("G_WORKTREE_BASE");
+    fn test_open_outside_src_root() {
+        let tmp = temp_dir("repo-open");
+        let layout = Layout::with_template(tmp.join("src"), DEFAULT_TEMPLATE).unwrap();
+        let remote = tmp.join("remote");
+        init_repo(&remote, &["a.txt"]);
+
+        let checkout = tmp.join("work/app");
+        run_git(
+            &tmp,
+            &[
+                "clone",
+                "--quiet",
+                remote.to_str().unwrap(),
+                checkout.to_str().unwrap(),
+            ],
+        )
+        .unwrap();
+        run_git(
+            &checkout,
+            &["remote", "set-url", "origin", "https://github.com/org/app"],
+        )
+        .unwrap();
+        let worktree = tmp.join("work/app-feature");
+        run_git(
+            &checkout,
+            &[
+                "worktree",
+                "add",
+                "--quiet",
+                "-b",
+                "feature",
+                worktree.to_str().unwrap(),
+            ],
+        )
+        .unwrap();
+
+        let info = RepoInfo::open(&layout, worktree.clone(), "origin").unwrap();
+        assert_eq!(info.repo_root, worktree);
+        assert_eq!(info.main_repo_dir, checkout);
+        assert_eq!(info.repo_path, "github.com/org/app");
+        assert_eq!(
+            info.worktree_base,
+            layout.worktree_dir("github.com/org/app").unwrap()
+        );
+        assert!(!info.is_bare);
+
+        // A configured remote the repository lacks falls back to its path.
+        let info = RepoInfo::open(&layout, checkout.clone(), "upstream").unwrap();
+        assert_eq!(info.remote_url, None);
+        assert_eq!(info.repo_path, format!("localhost{}", checkout.display()));
+
+        let separate = tmp.join("separate");
+        run_git(
+            &tmp,
+            &[
+                "clone",
+                "--quiet",
+                "--separate-git-dir",
+                tmp.join("separate.git").to_str().unwrap(),
+                remote.to_str().unwrap(),
+                separate.to_str().unwrap(),
+            ],
+        )
+        .unwrap();
+        let info = RepoInfo::open(&layout, separate.clone(), "origin").unwrap();
+        assert_eq!(info.main_repo_dir, separate);
+
+        let hub = tmp.join("hub");
+        run_git(
+            &tmp,
+            &[
+                "clone",
+                "--quiet",
+                "--bare",
+                remote.to_str().unwrap(),
+                hub.join(".git").to_str().unwrap(),
+            ],
+        )
+        .unwrap();
+        let info = RepoInfo::open(&layout, hub.clone(), "origin").unwrap();
+        assert_eq!(info.main_repo_dir, hub);
+        assert!(info.is_bare);
+
+        std::fs::remove_dir_all(&tmp).ok();
     }
 }
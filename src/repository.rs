@@ -1,13 +1,33 @@
 use anyhow::{Context, Result};
-use std::env;
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::PathBuf;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
-use crate::config::Config;
+use crate::config::{Config, ForkPlacement};
+use crate::discovery;
 use crate::fzf;
-
-pub fn list_repositories(config: &Config) -> Result<()> {
+use crate::git::{git_output, run_git};
+use crate::history::{self, History, SortOrder};
+use crate::identity;
+use crate::layout::Layout;
+use crate::path;
+use crate::relocate;
+use crate::repo;
+use crate::safety::{SafetyReport, WorktreeState};
+use crate::selection::Selection;
+use crate::shorthand;
+use crate::template;
+use crate::trash;
+use crate::worktree::Worktree;
+
+pub fn list_repositories(
+    config: &Config,
+    selection: &Selection,
+    refresh: bool,
+    tree: bool,
+) -> Result<()> {
     let src_root = get_src_root(config)?;
 
     if !src_root.exists() {
@@ -15,38 +35,100 @@ pub fn list_repositories(config: &Config) -> Result<()> {
         return Ok(());
     }
 
-    let repos = find_repositories(&src_root)?;
+    let repos = selection.filter(config, discovery::find_repositories(config, refresh)?)?;
 
     if repos.is_empty() {
-        println!("No repositories found in {}", src_root.display());
+        if selection.is_all() {
+            println!("No repositories found in {}", src_root.display());
+        } else {
+            println!("No repositories match {}", selection);
+        }
         return Ok(());
     }
 
-    for repo in repos {
-        let relative = repo
-            .strip_prefix(&src_root)
-            .unwrap_or(&repo)
-            .display()
-            .to_string();
-        println!("{}", relative);
+    let names: Vec<String> = repos
+        .iter()
+        .map(|repo| {
+            repo.strip_prefix(&src_root)
+                .unwrap_or(repo)
+                .display()
+                .to_string()
+        })
+        .collect();
+
+    if tree {
+        for line in format_tree(&names) {
+            println!("{}", line);
+        }
+    } else {
+        for name in names {
+            println!("{}", name);
+        }
     }
 
     Ok(())
 }
 
+/// Repository paths grouped by their first segment (the host with the
+/// default template) and the segments between it and the repository name
+/// (the owner):
+///
+/// ```text
+/// github.com
+///   poi2
+///     g
+/// ```
+fn format_tree(names: &[String]) -> Vec<String> {
+    let mut tree: BTreeMap<&str, BTreeMap<String, Vec<&str>>> = BTreeMap::new();
+
+    for name in names {
+        let segments: Vec<&str> = name.split('/').collect();
+        let (top, rest) = match segments.split_first() {
+            Some((top, rest)) if !rest.is_empty() => (*top, rest),
+            _ => ("", segments.as_slice()),
+        };
+        let (repo, owner) = rest.split_last().expect("path has a segment");
+        tree.entry(top)
+            .or_default()
+            .entry(owner.join("/"))
+            .or_default()
+            .push(repo);
+    }
+
+    let mut lines = Vec::new();
+    for (top, owners) in tree {
+        let mut indent = "";
+        if !top.is_empty() {
+            lines.push(top.to_string());
+            indent = "  ";
+        }
+        for (owner, repos) in owners {
+            let mut repo_indent = indent.to_string();
+            if !owner.is_empty() {
+                lines.push(format!("{}{}", indent, owner));
+                repo_indent.push_str("  ");
+            }
+            for repo in repos {
+                lines.push(format!("{}{}", repo_indent, repo));
+            }
+        }
+    }
+    lines
+}
+
 pub fn switch_repository(
     config: &Config,
     repository: Option<&str>,
     interactive: bool,
+    refresh: bool,
+    sort: SortOrder,
 ) -> Result<()> {
     let src_root = get_src_root(config)?;
 
     let target = if interactive {
-        let repos = find_repositories(&src_root)?;
+        let mut repos = interactive_candidates(config, repository, refresh)?;
 
-        if repos.is_empty() {
-            anyhow::bail!("No repositories found in {}", src_root.display());
-        }
+        History::open_for_sorting(config).sort(&mut repos, sort, |p| p.as_path());
 
         let items: Vec<String> = repos
             .iter()
@@ -66,23 +148,73 @@ pub fn switch_repository(
         anyhow::bail!("Repository not found: {}", target_path.display());
     }
 
-    println!("{}", target_path.display());
+    history::record(config, &target_path);
+    println!("{}", resolve_checkout(&target_path)?.display());
     Ok(())
 }
 
+/// Repositories offered by `switch -i` and `delete -i`: all of them, or
+/// those matching the pattern or `@group` given in place of a repository.
+fn interactive_candidates(
+    config: &Config,
+    filter: Option<&str>,
+    refresh: bool,
+) -> Result<Vec<PathBuf>> {
+    let filters: Vec<String> = filter.map(str::to_string).into_iter().collect();
+    let selection = Selection::new(config, &filters)?;
+    let repos = selection.filter(config, discovery::find_repositories(config, refresh)?)?;
+
+    if repos.is_empty() {
+        if selection.is_all() {
+            anyhow::bail!(
+                "No repositories found in {}",
+                get_src_root(config)?.display()
+            );
+        }
+        anyhow::bail!("No repositories match {}", selection);
+    }
+
+    Ok(repos)
+}
+
+/// A bare hub has no files to work on; switching to it lands in the
+/// worktree of its default branch instead.
+pub fn resolve_checkout(repo_dir: &Path) -> Result<PathBuf> {
+    if !repo::is_bare_repository(repo_dir) {
+        return Ok(repo_dir.to_path_buf());
+    }
+
+    let output = Command::new("git")
+        .args(["symbolic-ref", "--short", "HEAD"])
+        .current_dir(repo_dir)
+        .output()
+        .context("Failed to read HEAD of bare repository")?;
+    let default_branch = String::from_utf8(output.stdout)?.trim().to_string();
+
+    let worktrees = Worktree::list(&repo_dir.to_path_buf())?;
+    let checkout = worktrees
+        .iter()
+        .filter(|wt| !wt.is_bare)
+        .find(|wt| wt.branch.as_deref() == Some(default_branch.as_str()))
+        .or_else(|| worktrees.iter().find(|wt| !wt.is_bare))
+        .map(|wt| wt.path.clone());
+
+    Ok(checkout.unwrap_or_else(|| repo_dir.to_path_buf()))
+}
+
 pub fn delete_repository(
     config: &Config,
     repository: Option<&str>,
     interactive: bool,
+    refresh: bool,
+    yes: bool,
+    force: bool,
 ) -> Result<()> {
-    let src_root = get_src_root(config)?;
+    let layout = Layout::new(config)?;
+    let src_root = layout.root.clone();
 
     let target = if interactive {
-        let repos = find_repositories(&src_root)?;
-
-        if repos.is_empty() {
-            anyhow::bail!("No repositories found in {}", src_root.display());
-        }
+        let repos = interactive_candidates(config, repository, refresh)?;
 
         let items: Vec<String> = repos
             .iter()
@@ -96,43 +228,181 @@ pub fn delete_repository(
         anyhow::bail!("Repository name or --interactive flag required");
     };
 
-    let target_path = src_root.join(&target);
-
-    if !target_path.exists() {
-        anyhow::bail!("Repository not found: {}", target_path.display());
-    }
+    let target_path = layout.existing_dir(&target)?;
 
     if !is_git_repository(&target_path)? {
         anyhow::bail!("Not a git repository: {}", target_path.display());
     }
 
-    fs::remove_dir_all(&target_path)
-        .with_context(|| format!("Failed to delete repository: {}", target_path.display()))?;
+    let report = SafetyReport::collect(&target_path)?;
+    let linked: Vec<&WorktreeState> = report
+        .worktrees
+        .iter()
+        .filter(|wt| !wt.path.starts_with(&target_path))
+        .collect();
+
+    println!("Repository: {}", target);
+    println!("  Path: {}", target_path.display());
+    report.print();
+
+    if !report.is_safe() && !force {
+        anyhow::bail!(
+            "Refusing to delete {}: it has work that exists nowhere else.\n\
+            Push or remove it first, or use --force to delete anyway.",
+            target
+        );
+    }
+
+    if !yes
+        && !confirm(&format!(
+            "Move repository '{}' and {} worktree(s) to the trash?",
+            target,
+            linked.len()
+        ))?
+    {
+        println!("Cancelled");
+        return Ok(());
+    }
+
+    let worktrees: Vec<(PathBuf, Option<String>)> = linked
+        .iter()
+        .map(|wt| (wt.path.clone(), wt.branch.clone()))
+        .collect();
+    let entry = trash::move_to_trash(
+        &layout,
+        &target,
+        &target_path,
+        config.remote_name(),
+        &worktrees,
+    )?;
+
+    let worktree_base = layout.worktree_base();
+    for wt in linked {
+        if wt.path.starts_with(&worktree_base) {
+            relocate::remove_empty_parents(&wt.path, &worktree_base);
+        }
+        println!("Trashed worktree: {}", wt.path.display());
+    }
+    relocate::remove_empty_parents(&target_path, &src_root);
 
     println!("Deleted repository: {}", target);
+    println!("Moved to trash as {}", entry.id);
+    println!(
+        "  Restore with: g sonic-repository trash restore {}",
+        entry.id
+    );
     Ok(())
 }
 
-pub fn new_repository(config: &Config, repository: &str) -> Result<()> {
-    let src_root = get_src_root(config)?;
-    let target_path = src_root.join(repository);
+pub fn confirm(message: &str) -> Result<bool> {
+    print!("{} [y/N]: ", message);
+    io::stdout().flush()?;
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+
+    Ok(input.trim().eq_ignore_ascii_case("y"))
+}
+
+/// What `new` sets up besides `git init`.
+#[derive(Debug, Default)]
+pub struct NewOptions {
+    pub template: Option<String>,
+    pub initial_branch: Option<String>,
+    pub commit: bool,
+    pub origin: bool,
+}
+
+pub fn new_repository(config: &Config, repository: &str, options: &NewOptions) -> Result<()> {
+    let repository = repository.trim_matches('/');
+    let layout = Layout::new(config)?;
+
+    // `{host}/…` paths follow the layout template; anything else, such as a
+    // scratch repository, is created as given under the root.
+    let target_path = match repository.split_once('/') {
+        Some((host, _)) if host.contains('.') => layout.repo_dir(repository)?,
+        _ => {
+            if repository
+                .split('/')
+                .any(|s| s.is_empty() || s == "." || s == "..")
+            {
+                anyhow::bail!("Invalid repository path: {}", repository);
+            }
+            layout.root.join(repository)
+        }
+    };
 
     if target_path.exists() {
         anyhow::bail!("Repository already exists: {}", target_path.display());
     }
 
+    // Everything that can be rejected is checked before the directory exists.
+    let template = match options.template {
+        Some(ref name) => Some((
+            name,
+            template::find(config, name)?,
+            template::Variables::new(repository)?,
+        )),
+        None => None,
+    };
+
+    let origin = if options.origin {
+        let (host, path) = repository
+            .split_once('/')
+            .with_context(|| format!("Invalid repository path: {}", repository))?;
+        Some(shorthand::clone_url(host, path, config.protocol_for(host)))
+    } else {
+        None
+    };
+
     fs::create_dir_all(&target_path)
         .with_context(|| format!("Failed to create directory: {}", target_path.display()))?;
 
-    let status = Command::new("git")
-        .args(["init"])
-        .current_dir(&target_path)
-        .status()
-        .context("Failed to execute git init")?;
+    let init = || -> Result<()> {
+        let mut init = Command::new("git");
+        init.arg("init");
+        if let Some(ref branch) = options.initial_branch {
+            init.arg(format!("--initial-branch={}", branch));
+        }
+        let status = init
+            .current_dir(&target_path)
+            .status()
+            .context("Failed to execute git init")?;
+
+        if !status.success() {
+            anyhow::bail!("Failed to initialize git repository");
+        }
+
+        identity::apply(config, &target_path, Some(repository))?;
 
-    if !status.success() {
+        if let Some((name, dir, variables)) = template {
+            let count = template::apply(&dir, &target_path, &variables)?;
+            println!("✓ Template: {} ({} files)", name, count);
+        }
+
+        if let Some(url) = origin {
+            run_git(&target_path, &["remote", "add", "origin", &url])?;
+            println!("✓ Origin: {}", url);
+        }
+
+        if options.commit {
+            run_git(&target_path, &["add", "--all"])?;
+            run_git(
+                &target_path,
+                &["commit", "--quiet", "--allow-empty", "-m", "Initial commit"],
+            )?;
+            let branch = git_output(&target_path, &["branch", "--show-current"])?;
+            println!("✓ Initial commit on {}", branch);
+        }
+
+        Ok(())
+    };
+
+    // A half-built repository would block rerunning the command.
+    if let Err(e) = init() {
         fs::remove_dir_all(&target_path).ok();
-        anyhow::bail!("Failed to initialize git repository");
+        relocate::remove_empty_parents(&target_path, &layout.root);
+        return Err(e);
     }
 
     println!("Created repository: {}", repository);
@@ -140,49 +410,376 @@ pub fn new_repository(config: &Config, repository: &str) -> Result<()> {
     Ok(())
 }
 
-fn get_src_root(config: &Config) -> Result<std::path::PathBuf> {
-    if let Some(ref root) = config.root {
-        return Ok(PathBuf::from(root));
-    }
+pub fn migrate_repositories(config: &Config, template: Option<&str>, dry_run: bool) -> Result<()> {
+    let current = Layout::new(config)?;
+    let layout = match template {
+        Some(template) => Layout::with_template(current.root, template)?,
+        None => current,
+    };
 
-    let home = env::var("HOME").context("HOME environment variable not set")?;
-    Ok(PathBuf::from(home).join("src"))
-}
+    let repos = discovery::find_repositories(config, false)?;
+    let mut moved = 0;
+    let mut skipped = 0;
+
+    for repo_dir in repos {
+        let relative = repo_dir
+            .strip_prefix(&layout.root)
+            .unwrap_or(&repo_dir)
+            .display()
+            .to_string();
+
+        let repo_path =
+            match repo::placement_url(&repo_dir, config.fork_placement, config.remote_name())
+                .and_then(|url| path::parse_repo_path(&url))
+            {
+                Ok(repo_path) => repo_path,
+                Err(_) => {
+                    println!("Skipping {}: no parsable 'origin' remote", relative);
+                    skipped += 1;
+                    continue;
+                }
+            };
+
+        let target = layout.repo_dir(&repo_path)?;
+        if target == repo_dir {
+            continue;
+        }
 
-fn find_repositories(root: &std::path::Path) -> Result<Vec<PathBuf>> {
-    let mut repos = Vec::new();
+        let target_relative = layout.relative_path(&repo_path)?.display().to_string();
 
-    if let Ok(entries) = fs::read_dir(root) {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            if path.is_dir() {
-                collect_git_repos(&path, &mut repos)?;
-            }
+        if target.exists() {
+            println!("Skipping {}: {} already exists", relative, target_relative);
+            skipped += 1;
+            continue;
         }
+
+        println!("{} -> {}", relative, target_relative);
+        moved += 1;
+
+        if dry_run {
+            continue;
+        }
+
+        let worktrees_from = layout
+            .worktree_dir_for(&repo_dir)
+            .unwrap_or_else(|| layout.worktree_base().join(&relative));
+        let worktrees_to = layout.worktree_dir(&repo_path)?;
+
+        relocate::relocate(&repo_dir, &target, &worktrees_from, &worktrees_to)?;
+        relocate::remove_empty_parents(&repo_dir, &layout.root);
+        relocate::remove_empty_parents(&worktrees_from, &layout.worktree_base());
     }
 
-    repos.sort();
-    Ok(repos)
+    if dry_run {
+        println!("{} repositories would be moved, {} skipped", moved, skipped);
+    } else {
+        println!("Moved {} repositories, {} skipped", moved, skipped);
+    }
+
+    if template.is_some() && !dry_run {
+        println!(
+            "To keep using this layout, run:\n  git config --global sonic-git.layoutTemplate \"{}\"",
+            layout.template
+        );
+    }
+
+    Ok(())
 }
 
-fn collect_git_repos(dir: &std::path::Path, repos: &mut Vec<PathBuf>) -> Result<()> {
-    if dir.join(".git").exists() {
-        repos.push(dir.to_path_buf());
+/// Move a repository, with its worktree directory, to `destination` or, by
+/// default, to the location its `origin` maps to. With `check`, only list
+/// repositories whose location disagrees with their `origin`.
+pub fn move_repository(
+    config: &Config,
+    repository: Option<&str>,
+    destination: Option<&str>,
+    check: bool,
+) -> Result<()> {
+    let layout = Layout::new(config)?;
+
+    if check {
+        let repos = discovery::find_repositories(config, false)?;
+        let mismatched =
+            find_mismatched(&layout, config.fork_placement, config.remote_name(), &repos);
+
+        if mismatched.is_empty() {
+            println!("Every repository matches its origin");
+            return Ok(());
+        }
+
+        for (repo_dir, repo_path) in &mismatched {
+            println!(
+                "{} -> {}",
+                repo_dir
+                    .strip_prefix(&layout.root)
+                    .unwrap_or(repo_dir)
+                    .display(),
+                layout.relative_path(repo_path)?.display()
+            );
+        }
+        println!(
+            "{} repositories do not match their origin.\n\
+            Move one with: g sonic-repository mv <repository>",
+            mismatched.len()
+        );
         return Ok(());
     }
 
-    if let Ok(entries) = fs::read_dir(dir) {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            if path.is_dir() && !path.file_name().unwrap().to_str().unwrap().starts_with('.') {
-                collect_git_repos(&path, repos)?;
-            }
+    let repo_dir = match repository {
+        Some(repo) => layout.existing_dir(repo)?,
+        None => {
+            let cwd = std::env::current_dir().context("Failed to get current directory")?;
+            let git_dir = git_output(
+                &cwd,
+                &["rev-parse", "--path-format=absolute", "--git-common-dir"],
+            )?;
+            repo::hub_dir(Path::new(&git_dir))
         }
+    };
+
+    if !is_git_repository(&repo_dir)? {
+        anyhow::bail!("Not a git repository: {}", repo_dir.display());
     }
 
+    let repo_path = match destination {
+        Some(destination) => destination.trim_matches('/').to_string(),
+        None => path::parse_repo_path(&repo::placement_url(
+            &repo_dir,
+            config.fork_placement,
+            config.remote_name(),
+        )?)?,
+    };
+
+    let target = layout.repo_dir(&repo_path)?;
+    if target == repo_dir {
+        println!("Already at {}", target.display());
+        return Ok(());
+    }
+
+    let worktrees_from = layout
+        .worktree_dir_for(&repo_dir)
+        .with_context(|| format!("Not under the src root: {}", repo_dir.display()))?;
+    let worktrees_to = layout.worktree_dir(&repo_path)?;
+
+    relocate::relocate(&repo_dir, &target, &worktrees_from, &worktrees_to)?;
+    relocate::remove_empty_parents(&repo_dir, &layout.root);
+    relocate::remove_empty_parents(&worktrees_from, &layout.worktree_base());
+
+    println!("✓ Moved from: {}", repo_dir.display());
+
+    if destination.is_some()
+        && !find_mismatched(
+            &layout,
+            config.fork_placement,
+            config.remote_name(),
+            std::slice::from_ref(&target),
+        )
+        .is_empty()
+    {
+        println!(
+            "note: the new location does not match 'origin'. Update it with:\n  \
+            git remote set-url origin <url>"
+        );
+    }
+
+    println!("{}", target.display());
     Ok(())
 }
 
+/// Repositories whose location differs from where their `origin` maps to,
+/// with the canonical path they belong at. Those without a parsable `origin`
+/// are left out.
+fn find_mismatched(
+    layout: &Layout,
+    placement: ForkPlacement,
+    remote: &str,
+    repos: &[PathBuf],
+) -> Vec<(PathBuf, String)> {
+    repos
+        .iter()
+        .filter_map(|repo_dir| {
+            let repo_path = repo::placement_url(repo_dir, placement, remote)
+                .and_then(|url| path::parse_repo_path(&url))
+                .ok()?;
+            let expected = layout.repo_dir(&repo_path).ok()?;
+            (expected != *repo_dir).then(|| (repo_dir.clone(), repo_path))
+        })
+        .collect()
+}
+
+pub fn get_src_root(config: &Config) -> Result<std::path::PathBuf> {
+    Ok(Layout::new(config)?.root)
+}
+
 fn is_git_repository(path: &std::path::Path) -> Result<bool> {
     Ok(path.join(".git").exists())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::run_git;
+    use crate::layout::DEFAULT_TEMPLATE;
+    use crate::test_support::{init_repo, temp_dir};
+
+    #[test]
+    fn test_format_tree() {
+        let names: Vec<String> = [
+            "github.com/poi2/g",
+            "github.com/acme/api",
+            "github.com/poi2/dotfiles",
+            "gitlab.com/a/b/repo",
+            "example.com/solo",
+            "scratch",
+        ]
+        .iter()
+        .map(|name| name.to_string())
+        .collect();
+
+        assert_eq!(
+            format_tree(&names),
+            vec![
+                "scratch",
+                "example.com",
+                "  solo",
+                "github.com",
+                "  acme",
+                "    api",
+                "  poi2",
+                "    g",
+                "    dotfiles",
+                "gitlab.com",
+                "  a/b",
+                "    repo",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_find_mismatched_after_rename() {
+        let tmp = temp_dir("repository-mismatch");
+        let layout = Layout::with_template(tmp.clone(), DEFAULT_TEMPLATE).unwrap();
+
+        let renamed = tmp.join("github.com/old-org/app");
+        init_repo(&renamed, &["a.txt"]);
+        run_git(
+            &renamed,
+            &["remote", "add", "origin", "git@github.com:new-org/app.git"],
+        )
+        .unwrap();
+
+        let in_place = tmp.join("github.com/org/lib");
+        init_repo(&in_place, &["a.txt"]);
+        run_git(
+            &in_place,
+            &["remote", "add", "origin", "https://github.com/org/lib"],
+        )
+        .unwrap();
+
+        let local = tmp.join("example.com/me/scratch");
+        init_repo(&local, &["a.txt"]);
+
+        let mismatched = find_mismatched(
+            &layout,
+            ForkPlacement::Upstream,
+            "origin",
+            &[renamed.clone(), in_place, local],
+        );
+        assert_eq!(
+            mismatched,
+            vec![(renamed, "github.com/new-org/app".to_string())]
+        );
+
+        fs::remove_dir_all(&tmp).ok();
+    }
+
+    #[test]
+    fn test_delete_repository_stays_under_root() {
+        let tmp = temp_dir("repository-delete");
+        let root = tmp.join("src");
+        fs::create_dir_all(&root).unwrap();
+        let outside = tmp.join("outside");
+        init_repo(&outside, &["a.txt"]);
+
+        let config = Config {
+            root: Some(root.display().to_string()),
+            ..Default::default()
+        };
+        for target in ["../outside", outside.to_str().unwrap()] {
+            assert!(delete_repository(&config, Some(target), false, false, true, true).is_err());
+        }
+        assert!(outside.join(".git").is_dir());
+
+        fs::remove_dir_all(&tmp).ok();
+    }
+
+    #[test]
+    fn test_new_repository_validates_before_creating() {
+        let tmp = temp_dir("repository-new");
+        let config = Config {
+            root: Some(tmp.display().to_string()),
+            ..Default::default()
+        };
+
+        let origin = NewOptions {
+            origin: true,
+            ..Default::default()
+        };
+        assert!(new_repository(&config, "scratch", &origin).is_err());
+        assert!(!tmp.join("scratch").exists());
+
+        let template = NewOptions {
+            template: Some("missing".to_string()),
+            ..Default::default()
+        };
+        assert!(new_repository(&config, "example.com/me/app", &template).is_err());
+        assert!(!tmp.join("example.com").exists());
+
+        fs::remove_dir_all(&tmp).ok();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_new_repository_removes_partial_repository() {
+        let tmp = temp_dir("repository-new-partial");
+        let template = tmp.join("broken");
+        fs::create_dir_all(&template).unwrap();
+        std::os::unix::fs::symlink(tmp.join("missing"), template.join("dangling")).unwrap();
+
+        let root = tmp.join("src");
+        let config = Config {
+            root: Some(root.display().to_string()),
+            templates: [("broken".to_string(), template.display().to_string())].into(),
+            ..Default::default()
+        };
+        let options = NewOptions {
+            template: Some("broken".to_string()),
+            ..Default::default()
+        };
+
+        assert!(new_repository(&config, "example.com/me/app", &options).is_err());
+        assert!(!root.join("example.com").exists());
+
+        fs::remove_dir_all(&tmp).ok();
+    }
+
+    #[test]
+    fn test_new_repository_without_host() {
+        let tmp = temp_dir("repository-new-plain");
+        let config = Config {
+            root: Some(tmp.display().to_string()),
+            layout_template: Some("{host}/{repo}".to_string()),
+            ..Default::default()
+        };
+
+        for repository in ["scratch", "foo/bar"] {
+            new_repository(&config, repository, &NewOptions::default()).unwrap();
+            assert!(tmp.join(repository).join(".git").is_dir());
+        }
+        new_repository(&config, "github.com/acme/api", &NewOptions::default()).unwrap();
+        assert!(tmp.join("github.com/api/.git").is_dir());
+        assert!(new_repository(&config, "../escape", &NewOptions::default()).is_err());
+
+        fs::remove_dir_all(&tmp).ok();
+    }
+}
@@ -0,0 +1,185 @@
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+use crate::git::git_output;
+use crate::worktree::Worktree;
+
+/// Work in a repository that exists nowhere else: uncommitted changes,
+/// stashes, and local commits no remote-tracking ref contains.
+#[derive(Debug, Default)]
+pub struct SafetyReport {
+    pub worktrees: Vec<WorktreeState>,
+    pub stashes: usize,
+    /// Local branches with the number of commits not on any remote.
+    pub unpushed: Vec<(String, usize)>,
+}
+
+#[derive(Debug)]
+pub struct WorktreeState {
+    pub path: PathBuf,
+    pub branch: Option<String>,
+    pub changes: usize,
+    /// The directory is gone; git only keeps its administrative files.
+    pub missing: bool,
+}
+
+impl SafetyReport {
+    pub fn collect(repo_dir: &Path) -> Result<Self> {
+        let mut report = Self::default();
+
+        for wt in Worktree::list(&repo_dir.to_path_buf())? {
+            if wt.is_bare {
+                continue;
+            }
+
+            let missing = !wt.path.exists();
+            let changes = if missing {
+                0
+            } else {
+                git_output(&wt.path, &["status", "--porcelain"])?
+                    .lines()
+                    .count()
+            };
+
+            report.worktrees.push(WorktreeState {
+                path: wt.path,
+                branch: wt.branch,
+                changes,
+                missing,
+            });
+        }
+
+        // `git stash list` needs a work tree; the reflog also works in hubs.
+        report.stashes = git_output(repo_dir, &["reflog", "show", "--format=%H", "refs/stash"])
+            .map(|out| out.lines().count())
+            .unwrap_or(0);
+
+        let branches = git_output(
+            repo_dir,
+            &["for-each-ref", "--format=%(refname:lstrip=2)", "refs/heads"],
+        )?;
+        for branch in branches.lines() {
+            // The full ref, so a tag with the same name cannot shadow it.
+            let count: usize = git_output(
+                repo_dir,
+                &[
+                    "rev-list",
+                    "--count",
+                    &format!("refs/heads/{}", branch),
+                    "--not",
+                    "--remotes",
+                ],
+            )?
+            .parse()
+            .unwrap_or(0);
+            if count > 0 {
+                report.unpushed.push((branch.to_string(), count));
+            }
+        }
+
+        Ok(report)
+    }
+
+    pub fn is_safe(&self) -> bool {
+        self.stashes == 0
+            && self.unpushed.is_empty()
+            && self.worktrees.iter().all(|wt| wt.changes == 0)
+    }
+
+    pub fn print(&self) {
+        println!("  Worktrees: {}", self.worktrees.len());
+        for wt in &self.worktrees {
+            let branch = wt.branch.as_deref().unwrap_or("(detached)");
+            let state = if wt.missing {
+                " (missing)".to_string()
+            } else if wt.changes > 0 {
+                format!(" ({} uncommitted changes)", wt.changes)
+            } else {
+                String::new()
+            };
+            println!("    {:<20} {}{}", branch, wt.path.display(), state);
+        }
+
+        println!("  Stashes: {}", self.stashes);
+
+        if self.unpushed.is_empty() {
+            println!("  Unpushed commits: none");
+        } else {
+            println!("  Unpushed commits:");
+            for (branch, count) in &self.unpushed {
+                println!("    {:<20} {} not on any remote", branch, count);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::run_git;
+    use crate::test_support::{commit, init_repo, temp_dir};
+    use std::fs;
+
+    #[test]
+    fn test_safety_report() {
+        let tmp = temp_dir("safety");
+        let remote = tmp.join("remote");
+        init_repo(&remote, &["a.txt"]);
+
+        let repo = tmp.join("repo");
+        run_git(
+            &tmp,
+            &["clone", "--quiet", remote.to_str().unwrap(), "repo"],
+        )
+        .unwrap();
+
+        let report = SafetyReport::collect(&repo).unwrap();
+        assert!(report.is_safe());
+        assert_eq!(report.worktrees.len(), 1);
+
+        let feature = tmp.join("feature");
+        run_git(
+            &repo,
+            &[
+                "worktree",
+                "add",
+                "--quiet",
+                "-b",
+                "feature",
+                feature.to_str().unwrap(),
+            ],
+        )
+        .unwrap();
+        commit(&feature, "b.txt");
+        commit(&feature, "c.txt");
+        fs::write(repo.join("a.txt"), "changed").unwrap();
+        fs::write(feature.join("b.txt"), "changed").unwrap();
+        run_git(
+            &feature,
+            &[
+                "-c",
+                "user.name=test",
+                "-c",
+                "user.email=test@example.com",
+                "stash",
+                "--quiet",
+            ],
+        )
+        .unwrap();
+        // A pushed tag named like the branch must not hide its commits.
+        run_git(&repo, &["tag", "feature", "origin/main"]).unwrap();
+
+        let report = SafetyReport::collect(&repo).unwrap();
+        assert!(!report.is_safe());
+        assert_eq!(report.stashes, 1);
+        assert_eq!(report.unpushed, vec![("feature".to_string(), 2)]);
+        assert_eq!(report.worktrees[0].changes, 1);
+        assert_eq!(report.worktrees[1].changes, 0);
+
+        fs::remove_dir_all(&feature).unwrap();
+        let report = SafetyReport::collect(&repo).unwrap();
+        assert!(report.worktrees[1].missing);
+
+        fs::remove_dir_all(&tmp).ok();
+    }
+}
@@ -0,0 +1,147 @@
+use anyhow::Result;
+use std::path::PathBuf;
+
+use crate::config::Config;
+use crate::layout::Layout;
+use crate::pattern;
+use crate::pool;
+use crate::repo;
+
+/// Repositories chosen on the command line: patterns such as
+/// `github.com/acme` or `**/dotfiles`, and `@<name>` for the patterns of
+/// `sonic-git.group.<name>`. Without any, every repository is chosen.
+pub struct Selection {
+    args: Vec<String>,
+    patterns: Vec<String>,
+}
+
+impl Selection {
+    pub fn new(config: &Config, args: &[String]) -> Result<Self> {
+        let mut patterns = Vec::new();
+
+        for arg in args {
+            let Some(name) = arg.strip_prefix('@') else {
+                patterns.push(arg.clone());
+                continue;
+            };
+
+            match config.groups.get(&name.to_ascii_lowercase()) {
+                Some(group) => patterns.extend(group.iter().cloned()),
+                None => anyhow::bail!(
+                    "Unknown group: {}\n\
+                    Define it with: git config --global sonic-git.group.{} '<pattern> ...'",
+                    name,
+                    name
+                ),
+            }
+        }
+
+        Ok(Self {
+            args: args.to_vec(),
+            patterns,
+        })
+    }
+
+    pub fn is_all(&self) -> bool {
+        self.args.is_empty()
+    }
+
+    fn matches(&self, repo_path: &str) -> bool {
+        self.patterns
+            .iter()
+            .any(|pattern| pattern::matches(pattern, repo_path))
+    }
+
+    /// The repositories among `repos` this selection chooses. Patterns are
+    /// matched against both the path under the src root and the canonical
+    /// path, so they work whatever the layout template.
+    pub fn filter(&self, config: &Config, repos: Vec<PathBuf>) -> Result<Vec<PathBuf>> {
+        if self.is_all() {
+            return Ok(repos);
+        }
+
+        let layout = Layout::new(config)?;
+        let selected = pool::run(
+            &repos,
+            pool::default_jobs(),
+            |dir| {
+                let name = dir.strip_prefix(&layout.root).unwrap_or(dir);
+                self.matches(&name.display().to_string())
+                    || self.matches(&repo::canonical_path(config, &layout, dir))
+            },
+            |_, _, _| {},
+        );
+
+        Ok(repos
+            .into_iter()
+            .zip(selected)
+            .filter_map(|(dir, selected)| selected.then_some(dir))
+            .collect())
+    }
+}
+
+impl std::fmt::Display for Selection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.args.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::run_git;
+    use crate::test_support::{init_repo, temp_dir};
+    use std::collections::HashMap;
+    use std::fs;
+
+    #[test]
+    fn test_filter_by_pattern_and_group() {
+        let tmp = temp_dir("selection");
+        let config = Config {
+            root: Some(tmp.to_str().unwrap().to_string()),
+            layout_template: Some("{owner}/{repo}".to_string()),
+            groups: HashMap::from([(
+                "backend".to_string(),
+                vec![
+                    "github.com/acme/api".to_string(),
+                    "github.com/acme/worker".to_string(),
+                ],
+            )]),
+            ..Default::default()
+        };
+
+        let mut repos = Vec::new();
+        for (name, origin) in [
+            ("acme/api", "git@github.com:acme/api.git"),
+            ("acme/web", "https://github.com/acme/web"),
+            ("poi2/dotfiles", "https://gitlab.com/poi2/dotfiles"),
+        ] {
+            let dir = tmp.join(name);
+            init_repo(&dir, &[]);
+            run_git(&dir, &["remote", "add", "origin", origin]).unwrap();
+            repos.push(dir);
+        }
+
+        let names = |args: &[&str]| -> Vec<PathBuf> {
+            let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+            Selection::new(&config, &args)
+                .unwrap()
+                .filter(&config, repos.clone())
+                .unwrap()
+                .into_iter()
+                .map(|dir| dir.strip_prefix(&tmp).unwrap().to_path_buf())
+                .collect()
+        };
+
+        assert_eq!(names(&[]).len(), 3);
+        assert_eq!(names(&["@backend"]), vec![PathBuf::from("acme/api")]);
+        assert_eq!(names(&["github.com"]).len(), 2);
+        assert_eq!(
+            names(&["**/dotfiles", "acme/web"]),
+            vec![PathBuf::from("acme/web"), PathBuf::from("poi2/dotfiles")]
+        );
+        assert!(Selection::new(&config, &["@frontend".to_string()]).is_err());
+
+        fs::remove_dir_all(&tmp).ok();
+    }
+}
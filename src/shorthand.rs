@@ -0,0 +1,178 @@
+use anyhow::Result;
+
+use crate::config::{Config, Protocol};
+use crate::path;
+
+pub const DEFAULT_HOST: &str = "github.com";
+
+/// Host aliases available without any configuration. `sonic-git.host.<alias>`
+/// overrides them.
+const BUILTIN_HOST_ALIASES: &[(&str, &str)] = &[("gh", "github.com"), ("gl", "gitlab.com")];
+
+/// A repository named without a full URL: `org/repo`, `host/org/repo` or
+/// `alias:org/repo`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Shorthand {
+    pub host: String,
+    pub path: String,
+}
+
+impl Shorthand {
+    pub fn parse(input: &str, config: &Config) -> Option<Self> {
+        let input = input.trim().trim_end_matches('/');
+
+        if let Some((alias, path)) = input.split_once(':') {
+            let host = resolve_host_alias(alias, config)?;
+            return Self::with_path(host, path);
+        }
+
+        if input.contains("://") || input.starts_with('/') || input.starts_with('.') {
+            return None;
+        }
+
+        let (first, rest) = input.split_once('/')?;
+        if first.contains('.') {
+            Self::with_path(first.to_ascii_lowercase(), rest)
+        } else {
+            let host = config
+                .default_host
+                .clone()
+                .unwrap_or_else(|| DEFAULT_HOST.to_string());
+            Self::with_path(host, input)
+        }
+    }
+
+    fn with_path(host: String, path: &str) -> Option<Self> {
+        let path = path.trim_matches('/');
+        let path = path.strip_suffix(".git").unwrap_or(path);
+
+        if !path.contains('/') || path.split('/').any(|s| s.is_empty() || s == "..") {
+            return None;
+        }
+
+        Some(Self {
+            host,
+            path: path.to_string(),
+        })
+    }
+
+    pub fn url(&self, protocol: Protocol) -> String {
+        clone_url(&self.host, &self.path, protocol)
+    }
+}
+
+pub fn clone_url(host: &str, path: &str, protocol: Protocol) -> String {
+    match protocol {
+        Protocol::Https => format!("https://{}/{}.git", host, path),
+        Protocol::Ssh => format!("git@{}:{}.git", host, path),
+    }
+}
+
+fn resolve_host_alias(alias: &str, config: &Config) -> Option<String> {
+    let alias = alias.to_ascii_lowercase();
+
+    config.host_aliases.get(&alias).cloned().or_else(|| {
+        BUILTIN_HOST_ALIASES
+            .iter()
+            .find(|(name, _)| *name == alias)
+            .map(|(_, host)| host.to_string())
+    })
+}
+
+/// Turn whatever the user typed into the URL to clone.
+///
+/// Full URLs are returned untouched; shorthands are expanded with the
+/// protocol preferred for their host.
+pub fn resolve_clone_url(input: &str, config: &Config) -> Result<String> {
+    if let Some(shorthand) = Shorthand::parse(input, config) {
+        return Ok(shorthand.url(config.protocol_for(&shorthand.host)));
+    }
+
+    if let Some((alias, _)) = input.split_once(':') {
+        if resolve_host_alias(alias, config).is_some() {
+            anyhow::bail!(
+                "Invalid shorthand: {}\n\
+                Expected {}:<owner>/<repo>",
+                input,
+                alias
+            );
+        }
+    }
+
+    path::parse_repo_path(input)?;
+    Ok(input.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn config() -> Config {
+        let mut host_aliases = HashMap::new();
+        host_aliases.insert("work".to_string(), "git.example.com".to_string());
+        host_aliases.insert("gl".to_string(), "gitlab.example.com".to_string());
+
+        let mut host_protocols = HashMap::new();
+        host_protocols.insert("git.example.com".to_string(), Protocol::Ssh);
+
+        Config {
+            default_host: Some("github.com".to_string()),
+            host_aliases,
+            host_protocols,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_resolve_clone_url() {
+        let config = config();
+        let cases = [
+            ("org/repo", "https://github.com/org/repo.git"),
+            ("org/repo.git", "https://github.com/org/repo.git"),
+            ("gh:org/repo", "https://github.com/org/repo.git"),
+            (
+                "gl:group/sub/repo",
+                "https://gitlab.example.com/group/sub/repo.git",
+            ),
+            ("work:team/app", "git@git.example.com:team/app.git"),
+            ("GitLab.com/group/repo", "https://gitlab.com/group/repo.git"),
+            (
+                "https://github.com/org/repo.git",
+                "https://github.com/org/repo.git",
+            ),
+            ("git@github.com:org/repo.git", "git@github.com:org/repo.git"),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(
+                resolve_clone_url(input, &config).unwrap(),
+                expected,
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_default_host_from_config() {
+        let config = Config {
+            default_host: Some("gitlab.com".to_string()),
+            ..Default::default()
+        };
+        let shorthand = Shorthand::parse("org/repo", &config).unwrap();
+        assert_eq!(shorthand.host, "gitlab.com");
+        assert_eq!(shorthand.path, "org/repo");
+    }
+
+    #[test]
+    fn test_shorthand_rejects_incomplete_paths() {
+        let config = config();
+        assert_eq!(Shorthand::parse("repo", &config), None);
+        assert_eq!(Shorthand::parse("gh:repo", &config), None);
+        assert_eq!(Shorthand::parse("gh:org/../repo", &config), None);
+        assert_eq!(Shorthand::parse("/srv/git/repo.git", &config), None);
+        assert!(resolve_clone_url("repo", &config).is_err());
+        assert!(resolve_clone_url("gh:repo", &config).is_err());
+    }
+}
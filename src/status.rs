@@ -0,0 +1,235 @@
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+use crate::age;
+use crate::config::Config;
+use crate::discovery;
+use crate::git::{ahead_behind, git_output};
+use crate::layout::Layout;
+use crate::pool;
+use crate::repository;
+use crate::selection::Selection;
+use crate::worktree::Worktree;
+
+/// What `status` shows for one repository.
+#[derive(Debug, Default)]
+struct RepoStatus {
+    branch: Option<String>,
+    changes: usize,
+    upstream: Option<(u32, u32)>,
+    stashes: usize,
+    worktrees: usize,
+    last_commit: Option<u64>,
+}
+
+impl RepoStatus {
+    fn is_dirty(&self) -> bool {
+        self.changes > 0
+    }
+
+    /// Commits not on any remote: ahead of the upstream, or a branch with
+    /// commits that has no upstream at all.
+    fn is_unpushed(&self) -> bool {
+        match self.upstream {
+            Some((ahead, _)) => ahead > 0,
+            None => self.branch.is_some() && self.last_commit.is_some(),
+        }
+    }
+}
+
+pub fn show_status(
+    config: &Config,
+    selection: &Selection,
+    dirty_only: bool,
+    unpushed_only: bool,
+) -> Result<()> {
+    let layout = Layout::new(config)?;
+    let repos = selection.filter(config, discovery::find_repositories(config, false)?)?;
+
+    if repos.is_empty() {
+        println!("No repositories found in {}", layout.root.display());
+        return Ok(());
+    }
+
+    let worktree_base = layout.worktree_base();
+    let statuses = pool::run(
+        &repos,
+        pool::default_jobs(),
+        |dir| collect_status(dir, &worktree_base),
+        |_, _, _| {},
+    );
+
+    let now = age::now();
+
+    let mut rows = vec![[
+        "REPOSITORY".to_string(),
+        "BRANCH".to_string(),
+        "DIRTY".to_string(),
+        "AHEAD/BEHIND".to_string(),
+        "STASH".to_string(),
+        "WORKTREES".to_string(),
+        "LAST COMMIT".to_string(),
+    ]];
+
+    for (dir, status) in repos.iter().zip(&statuses) {
+        if (dirty_only && !status.is_dirty()) || (unpushed_only && !status.is_unpushed()) {
+            continue;
+        }
+
+        let name = dir.strip_prefix(&layout.root).unwrap_or(dir);
+        rows.push([
+            name.display().to_string(),
+            status
+                .branch
+                .clone()
+                .unwrap_or_else(|| "(detached)".to_string()),
+            if status.is_dirty() {
+                format!("*{}", status.changes)
+            } else {
+                "-".to_string()
+            },
+            match status.upstream {
+                Some((ahead, behind)) => format!("↑{} ↓{}", ahead, behind),
+                None => "-".to_string(),
+            },
+            status.stashes.to_string(),
+            status.worktrees.to_string(),
+            match status.last_commit {
+                Some(time) => age::format_age(now.saturating_sub(time)),
+                None => "-".to_string(),
+            },
+        ]);
+    }
+
+    if rows.len() == 1 {
+        println!("No matching repositories");
+        return Ok(());
+    }
+
+    print_table(&rows);
+    Ok(())
+}
+
+fn collect_status(repo_dir: &Path, worktree_base: &Path) -> RepoStatus {
+    let checkout =
+        repository::resolve_checkout(repo_dir).unwrap_or_else(|_| repo_dir.to_path_buf());
+
+    let branch = git_output(&checkout, &["branch", "--show-current"])
+        .ok()
+        .filter(|b| !b.is_empty());
+    let changes = git_output(&checkout, &["status", "--porcelain"])
+        .map(|out| out.lines().count())
+        .unwrap_or(0);
+    let stashes = git_output(&checkout, &["stash", "list"])
+        .map(|out| out.lines().count())
+        .unwrap_or(0);
+    let last_commit = git_output(&checkout, &["log", "-1", "--format=%ct"])
+        .ok()
+        .and_then(|time| time.parse().ok());
+    let worktrees = Worktree::list(&PathBuf::from(repo_dir))
+        .map(|wts| {
+            wts.iter()
+                .filter(|wt| !wt.is_bare && wt.path.starts_with(worktree_base))
+                .count()
+        })
+        .unwrap_or(0);
+
+    RepoStatus {
+        branch,
+        changes,
+        upstream: ahead_behind(&checkout),
+        stashes,
+        worktrees,
+        last_commit,
+    }
+}
+
+fn print_table(rows: &[[String; 7]]) {
+    let mut widths = [0; 7];
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    for row in rows {
+        let line: Vec<String> = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| {
+                let pad = width - cell.chars().count();
+                format!("{}{}", cell, " ".repeat(pad))
+            })
+            .collect();
+        println!("{}", line.join("  ").trim_end());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::run_git;
+    use crate::test_support::{commit, init_repo, temp_dir};
+    use std::fs;
+
+    #[test]
+    fn test_collect_status() {
+        let tmp = temp_dir("status");
+        let remote = tmp.join("remote");
+        init_repo(&remote, &["a.txt"]);
+
+        let repo = tmp.join("src/repo");
+        run_git(
+            &tmp,
+            &["clone", "--quiet", remote.to_str().unwrap(), "src/repo"],
+        )
+        .unwrap();
+        commit(&repo, "b.txt");
+        fs::write(repo.join("dirty.txt"), "x").unwrap();
+
+        let worktree_base = tmp.join("worktrees");
+        run_git(
+            &repo,
+            &[
+                "worktree",
+                "add",
+                "--quiet",
+                "-b",
+                "feature",
+                worktree_base.join("feature").to_str().unwrap(),
+            ],
+        )
+        .unwrap();
+
+        let status = collect_status(&repo, &worktree_base);
+        assert_eq!(status.branch.as_deref(), Some("main"));
+        assert_eq!(status.changes, 1);
+        assert_eq!(status.upstream, Some((1, 0)));
+        assert_eq!(status.stashes, 0);
+        assert_eq!(status.worktrees, 1);
+        assert!(status.is_dirty());
+        assert!(status.is_unpushed());
+
+        run_git(
+            &repo,
+            &[
+                "-c",
+                "user.name=test",
+                "-c",
+                "user.email=test@example.com",
+                "stash",
+                "push",
+                "--quiet",
+                "--include-untracked",
+            ],
+        )
+        .unwrap();
+        run_git(&repo, &["reset", "--quiet", "--hard", "origin/main"]).unwrap();
+        let status = collect_status(&repo, &worktree_base);
+        assert_eq!(status.stashes, 1);
+        assert!(!status.is_dirty());
+        assert!(!status.is_unpushed());
+
+        fs::remove_dir_all(&tmp).ok();
+    }
+}
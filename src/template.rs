@@ -0,0 +1,178 @@
+use anyhow::{Context, Result};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::age;
+use crate::config::Config;
+
+/// Values substituted for `{{host}}`, `{{owner}}`, `{{repo}}` and
+/// `{{year}}` in template file names and contents.
+#[derive(Debug, PartialEq)]
+pub struct Variables {
+    pub host: String,
+    pub owner: String,
+    pub repo: String,
+    pub year: i64,
+}
+
+impl Variables {
+    /// Variables for a canonical `{host}/{path}`; `owner` takes every
+    /// segment between host and repository, as in layout templates.
+    pub fn new(repo_path: &str) -> Result<Self> {
+        let segments: Vec<&str> = repo_path.split('/').filter(|s| !s.is_empty()).collect();
+        let [host, middle @ .., repo] = segments.as_slice() else {
+            anyhow::bail!("Invalid repository path: {}", repo_path);
+        };
+
+        Ok(Self {
+            host: host.to_string(),
+            owner: middle.join("/"),
+            repo: repo.to_string(),
+            year: age::year(age::now()),
+        })
+    }
+
+    fn render(&self, text: &str) -> String {
+        text.replace("{{host}}", &self.host)
+            .replace("{{owner}}", &self.owner)
+            .replace("{{repo}}", &self.repo)
+            .replace("{{year}}", &self.year.to_string())
+    }
+}
+
+/// Directory of template `name`: `sonic-git.template.<name>`, else
+/// `<templates dir>/<name>`.
+pub fn find(config: &Config, name: &str) -> Result<PathBuf> {
+    let dir = match config.templates.get(&name.to_ascii_lowercase()) {
+        Some(dir) => PathBuf::from(dir),
+        None => templates_dir(config)
+            .context("HOME environment variable not set")?
+            .join(name),
+    };
+
+    if !dir.is_dir() {
+        anyhow::bail!(
+            "Template not found: {} ({})\n\
+            Create the directory, or point sonic-git.template.{} at one.",
+            name,
+            dir.display(),
+            name
+        );
+    }
+
+    Ok(dir)
+}
+
+/// `sonic-git.templatesDir`, or `$XDG_CONFIG_HOME/sonic-git/templates`
+/// (`~/.config/sonic-git/templates`).
+fn templates_dir(config: &Config) -> Option<PathBuf> {
+    if let Some(ref dir) = config.templates_dir {
+        return Some(PathBuf::from(dir));
+    }
+
+    let config_home = env::var("XDG_CONFIG_HOME")
+        .ok()
+        .filter(|dir| Path::new(dir).is_absolute())
+        .map(PathBuf::from)
+        .or_else(|| {
+            env::var("HOME")
+                .ok()
+                .map(|home| Path::new(&home).join(".config"))
+        })?;
+
+    Some(config_home.join("sonic-git").join("templates"))
+}
+
+/// Copy the template at `template` into `target`, substituting variables in
+/// paths and in every file that is valid UTF-8. Returns the number of files
+/// written.
+pub fn apply(template: &Path, target: &Path, variables: &Variables) -> Result<usize> {
+    let mut count = 0;
+
+    let entries =
+        fs::read_dir(template).with_context(|| format!("Failed to read {}", template.display()))?;
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name();
+        if name == ".git" {
+            continue;
+        }
+
+        let from = entry.path();
+        let to = target.join(variables.render(&name.to_string_lossy()));
+
+        if from.is_dir() {
+            fs::create_dir_all(&to)
+                .with_context(|| format!("Failed to create {}", to.display()))?;
+            count += apply(&from, &to, variables)?;
+            continue;
+        }
+
+        let content =
+            fs::read(&from).with_context(|| format!("Failed to read {}", from.display()))?;
+        let content = match String::from_utf8(content) {
+            Ok(text) => variables.render(&text).into_bytes(),
+            Err(binary) => binary.into_bytes(),
+        };
+        fs::write(&to, content).with_context(|| format!("Failed to write {}", to.display()))?;
+        // Keep executable bits, e.g. for scripts.
+        fs::set_permissions(&to, fs::metadata(&from)?.permissions())?;
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    #[test]
+    fn test_variables() {
+        let vars = Variables::new("gitlab.com/a/b/repo").unwrap();
+        assert_eq!(vars.host, "gitlab.com");
+        assert_eq!(vars.owner, "a/b");
+        assert_eq!(vars.repo, "repo");
+        assert!(Variables::new("repo").is_err());
+    }
+
+    #[test]
+    fn test_apply() {
+        let tmp = temp_dir("template");
+        let template = tmp.join("template");
+        fs::create_dir_all(template.join("src/{{repo}}")).unwrap();
+        fs::create_dir_all(template.join(".git")).unwrap();
+        fs::write(
+            template.join("README.md"),
+            "# {{repo}}\n{{owner}} on {{host}}, {{year}}\n",
+        )
+        .unwrap();
+        fs::write(template.join("src/{{repo}}/lib.rs"), "").unwrap();
+        fs::write(template.join("logo.bin"), [0xff, 0xfe, b'{']).unwrap();
+
+        let target = tmp.join("target");
+        fs::create_dir_all(&target).unwrap();
+        let vars = Variables {
+            host: "github.com".to_string(),
+            owner: "acme".to_string(),
+            repo: "api".to_string(),
+            year: 2026,
+        };
+
+        assert_eq!(apply(&template, &target, &vars).unwrap(), 3);
+        assert_eq!(
+            fs::read_to_string(target.join("README.md")).unwrap(),
+            "# api\nacme on github.com, 2026\n"
+        );
+        assert!(target.join("src/api/lib.rs").exists());
+        assert_eq!(
+            fs::read(target.join("logo.bin")).unwrap(),
+            [0xff, 0xfe, b'{']
+        );
+        assert!(!target.join(".git").exists());
+
+        fs::remove_dir_all(&tmp).ok();
+    }
+}
@@ -0,0 +1,42 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::git::run_git;
+
+/// A fresh, empty directory under the system temp dir, unique per test name
+/// and process.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("sonic-git-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Initialize a repository on `main` with one commit per file.
+pub fn init_repo(dir: &Path, files: &[&str]) {
+    fs::create_dir_all(dir).unwrap();
+    run_git(dir, &["init", "--quiet", "--initial-branch=main"]).unwrap();
+    for file in files {
+        commit(dir, file);
+    }
+}
+
+pub fn commit(dir: &Path, file: &str) {
+    fs::write(dir.join(file), file).unwrap();
+    run_git(dir, &["add", file]).unwrap();
+    run_git(
+        dir,
+        &[
+            "-c",
+            "user.name=test",
+            "-c",
+            "user.email=test@example.com",
+            "commit",
+            "--quiet",
+            "-m",
+            file,
+        ],
+    )
+    .unwrap();
+}
@@ -0,0 +1,401 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::age;
+use crate::config::Config;
+use crate::layout::Layout;
+use crate::relocate;
+use crate::repo;
+
+const METADATA_FILE: &str = "trash.toml";
+
+/// What `restore` needs to put a deleted repository back.
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct TrashMetadata {
+    /// Repository path relative to the src root, as given to `delete`.
+    pub repository: String,
+    pub original_path: PathBuf,
+    pub remote_url: Option<String>,
+    pub deleted_at: u64,
+    #[serde(default, rename = "worktree")]
+    pub worktrees: Vec<TrashedWorktree>,
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct TrashedWorktree {
+    pub original_path: PathBuf,
+    pub branch: Option<String>,
+}
+
+/// One deleted repository: `<trash>/<timestamp>-<name>/` holding `repo/`,
+/// `worktrees/<n>/` and the metadata file.
+pub struct TrashEntry {
+    pub id: String,
+    pub dir: PathBuf,
+    pub metadata: TrashMetadata,
+}
+
+impl TrashEntry {
+    fn repo_dir(&self) -> PathBuf {
+        self.dir.join("repo")
+    }
+
+    fn worktree_dir(&self, index: usize) -> PathBuf {
+        self.dir.join("worktrees").join(index.to_string())
+    }
+}
+
+/// Move a repository and its linked worktrees into the trash.
+pub fn move_to_trash(
+    layout: &Layout,
+    repository: &str,
+    repo_dir: &Path,
+    remote: &str,
+    worktrees: &[(PathBuf, Option<String>)],
+) -> Result<TrashEntry> {
+    let deleted_at = age::now();
+    let id = format!(
+        "{}-{}",
+        age::format_timestamp(deleted_at),
+        repository.replace('/', "_")
+    );
+
+    let entry = TrashEntry {
+        dir: layout.trash_dir().join(&id),
+        id,
+        metadata: TrashMetadata {
+            repository: repository.to_string(),
+            original_path: repo_dir.to_path_buf(),
+            remote_url: repo::find_remote_url(repo_dir, remote),
+            deleted_at,
+            worktrees: worktrees
+                .iter()
+                .map(|(path, branch)| TrashedWorktree {
+                    original_path: path.clone(),
+                    branch: branch.clone(),
+                })
+                .collect(),
+        },
+    };
+
+    if entry.dir.exists() {
+        anyhow::bail!("Trash entry already exists: {}", entry.dir.display());
+    }
+    fs::create_dir_all(&entry.dir)
+        .with_context(|| format!("Failed to create {}", entry.dir.display()))?;
+
+    let metadata = toml::to_string_pretty(&entry.metadata)?;
+    fs::write(entry.dir.join(METADATA_FILE), metadata)
+        .with_context(|| format!("Failed to write trash metadata in {}", entry.dir.display()))?;
+
+    // Worktrees first, then the repository. If anything cannot be moved,
+    // what was already moved goes back and the entry is removed.
+    let mut moves: Vec<(PathBuf, PathBuf)> = entry
+        .metadata
+        .worktrees
+        .iter()
+        .enumerate()
+        .filter(|(_, wt)| wt.original_path.exists())
+        .map(|(index, wt)| (wt.original_path.clone(), entry.worktree_dir(index)))
+        .collect();
+    moves.push((repo_dir.to_path_buf(), entry.repo_dir()));
+
+    for (done, (from, to)) in moves.iter().enumerate() {
+        if let Err(e) = relocate::move_dir(from, to) {
+            for (from, to) in moves[..done].iter().rev() {
+                relocate::move_dir(to, from).ok();
+            }
+            fs::remove_dir_all(&entry.dir).ok();
+            return Err(e);
+        }
+    }
+
+    Ok(entry)
+}
+
+fn load_entries(layout: &Layout) -> Result<Vec<TrashEntry>> {
+    let mut entries = Vec::new();
+
+    let Ok(dirs) = fs::read_dir(layout.trash_dir()) else {
+        return Ok(entries);
+    };
+
+    for dir in dirs.flatten() {
+        let path = dir.path();
+        let metadata_file = path.join(METADATA_FILE);
+        if !metadata_file.exists() {
+            continue;
+        }
+
+        let content = fs::read_to_string(&metadata_file)
+            .with_context(|| format!("Failed to read {}", metadata_file.display()))?;
+        let metadata = toml::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", metadata_file.display()))?;
+
+        entries.push(TrashEntry {
+            id: dir.file_name().to_string_lossy().into_owned(),
+            dir: path,
+            metadata,
+        });
+    }
+
+    entries.sort_by_key(|e| std::cmp::Reverse(e.metadata.deleted_at));
+    Ok(entries)
+}
+
+pub fn list_trash(config: &Config) -> Result<()> {
+    let layout = Layout::new(config)?;
+    let entries = load_entries(&layout)?;
+
+    if entries.is_empty() {
+        println!("Trash is empty");
+        return Ok(());
+    }
+
+    let now = age::now();
+    for entry in entries {
+        println!(
+            "{}  {:>4} ago  {} ({} worktrees)",
+            entry.id,
+            age::format_age(now.saturating_sub(entry.metadata.deleted_at)),
+            entry.metadata.repository,
+            entry.metadata.worktrees.len()
+        );
+    }
+
+    Ok(())
+}
+
+/// Put a trashed repository and its worktrees back where they were.
+///
+/// `target` is a trash id or a repository path; for a path the most recently
+/// deleted copy is restored.
+pub fn restore(config: &Config, target: &str) -> Result<()> {
+    let layout = Layout::new(config)?;
+    let entries = load_entries(&layout)?;
+
+    let entry = entries
+        .iter()
+        .find(|e| e.id == target)
+        .or_else(|| {
+            entries
+                .iter()
+                .find(|e| e.metadata.repository == target.trim_matches('/'))
+        })
+        .ok_or_else(|| anyhow::anyhow!("Not in trash: {}", target))?;
+
+    restore_entry(entry)?;
+
+    println!("✓ Restored: {}", entry.metadata.original_path.display());
+    for wt in &entry.metadata.worktrees {
+        println!("✓ Restored worktree: {}", wt.original_path.display());
+    }
+    Ok(())
+}
+
+fn restore_entry(entry: &TrashEntry) -> Result<()> {
+    let metadata = &entry.metadata;
+
+    for path in std::iter::once(&metadata.original_path)
+        .chain(metadata.worktrees.iter().map(|wt| &wt.original_path))
+    {
+        if path.exists() {
+            anyhow::bail!(
+                "Cannot restore {}: {} already exists",
+                metadata.repository,
+                path.display()
+            );
+        }
+    }
+
+    // The repository first, then its worktrees. If anything fails, what was
+    // already restored goes back so the entry can be restored again.
+    let mut moves = vec![(entry.repo_dir(), metadata.original_path.clone())];
+    moves.extend(
+        metadata
+            .worktrees
+            .iter()
+            .enumerate()
+            .map(|(index, wt)| (entry.worktree_dir(index), wt.original_path.clone()))
+            .filter(|(from, _)| from.exists()),
+    );
+
+    let roll_back = |done: usize| {
+        for (from, to) in moves[..done].iter().rev() {
+            relocate::move_dir(to, from).ok();
+        }
+    };
+
+    for (done, (from, to)) in moves.iter().enumerate() {
+        if let Err(e) = relocate::move_dir(from, to) {
+            roll_back(done);
+            return Err(e);
+        }
+    }
+
+    let restored: Vec<PathBuf> = moves[1..].iter().map(|(_, to)| to.clone()).collect();
+    if !restored.is_empty() {
+        if let Err(e) = relocate::repair_worktrees(&metadata.original_path, &restored) {
+            roll_back(moves.len());
+            return Err(e);
+        }
+    }
+
+    fs::remove_dir_all(&entry.dir)
+        .with_context(|| format!("Failed to remove {}", entry.dir.display()))?;
+    Ok(())
+}
+
+/// Permanently delete trashed repositories, all of them or those deleted
+/// more than `older_than` ago.
+pub fn empty_trash(config: &Config, older_than: Option<&str>) -> Result<()> {
+    let layout = Layout::new(config)?;
+    let max_age = older_than.map(age::parse_age).transpose()?;
+    let now = age::now();
+
+    let mut removed = 0;
+    for entry in load_entries(&layout)? {
+        if max_age.is_some_and(|max| now.saturating_sub(entry.metadata.deleted_at) <= max) {
+            continue;
+        }
+
+        fs::remove_dir_all(&entry.dir)
+            .with_context(|| format!("Failed to delete {}", entry.dir.display()))?;
+        println!("Deleted: {}", entry.id);
+        removed += 1;
+    }
+
+    println!("✓ Removed {} repositories from the trash", removed);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::{git_output, run_git};
+    use crate::layout::DEFAULT_TEMPLATE;
+    use crate::test_support::{init_repo, temp_dir};
+
+    #[test]
+    fn test_trash_and_restore_relinks_worktrees() {
+        let tmp = temp_dir("trash");
+        let layout = Layout::with_template(tmp.join("src"), DEFAULT_TEMPLATE).unwrap();
+
+        let repo = tmp.join("src/example.com/org/repo");
+        init_repo(&repo, &["a.txt"]);
+        let worktree = tmp.join("src/.worktrees/example.com/org/repo/feature");
+        run_git(
+            &repo,
+            &[
+                "worktree",
+                "add",
+                "--quiet",
+                "-b",
+                "feature",
+                worktree.to_str().unwrap(),
+            ],
+        )
+        .unwrap();
+
+        let entry = move_to_trash(
+            &layout,
+            "example.com/org/repo",
+            &repo,
+            "origin",
+            &[(worktree.clone(), Some("feature".to_string()))],
+        )
+        .unwrap();
+        assert!(!repo.exists());
+        assert!(!worktree.exists());
+        assert!(entry.repo_dir().join(".git").exists());
+
+        let entries = load_entries(&layout).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].metadata, entry.metadata);
+
+        restore_entry(&entries[0]).unwrap();
+        assert!(!entry.dir.exists());
+        let branch = git_output(&worktree, &["branch", "--show-current"]).unwrap();
+        assert_eq!(branch, "feature");
+        let list = git_output(&repo, &["worktree", "list", "--porcelain"]).unwrap();
+        assert!(!list.contains("prunable"));
+
+        // A repository that cannot be moved brings its worktrees back.
+        let missing = tmp.join("src/example.com/org/missing");
+        assert!(move_to_trash(
+            &layout,
+            "example.com/org/missing",
+            &missing,
+            "origin",
+            &[(worktree.clone(), Some("feature".to_string()))],
+        )
+        .is_err());
+        assert!(worktree.join(".git").exists());
+        assert!(load_entries(&layout).unwrap().is_empty());
+
+        // A worktree that cannot be restored sends the repository back.
+        let entry = move_to_trash(
+            &layout,
+            "example.com/org/repo",
+            &repo,
+            "origin",
+            &[(worktree.clone(), Some("feature".to_string()))],
+        )
+        .unwrap();
+        let blocker = worktree.parent().unwrap();
+        fs::remove_dir_all(blocker).ok();
+        fs::write(blocker, "").unwrap();
+        assert!(restore_entry(&entry).is_err());
+        assert!(!repo.exists());
+        assert!(entry.repo_dir().join(".git").exists());
+
+        fs::remove_file(blocker).unwrap();
+        restore_entry(&entry).unwrap();
+        assert!(worktree.join(".git").exists());
+
+        fs::remove_dir_all(&tmp).ok();
+    }
+
+    #[test]
+    fn test_empty_older_than() {
+        let tmp = temp_dir("trash-empty");
+        let config = Config {
+            root: Some(tmp.to_str().unwrap().to_string()),
+            ..Default::default()
+        };
+        let layout = Layout::new(&config).unwrap();
+
+        let now = age::now();
+        for (id, deleted_at) in [("old", now - 40 * 24 * 3600), ("new", now - 60)] {
+            let dir = layout.trash_dir().join(id);
+            fs::create_dir_all(dir.join("repo")).unwrap();
+            let metadata = TrashMetadata {
+                repository: format!("example.com/org/{}", id),
+                original_path: tmp.join(id),
+                remote_url: None,
+                deleted_at,
+                worktrees: Vec::new(),
+            };
+            fs::write(
+                dir.join(METADATA_FILE),
+                toml::to_string_pretty(&metadata).unwrap(),
+            )
+            .unwrap();
+        }
+
+        empty_trash(&config, Some("30d")).unwrap();
+        let ids: Vec<String> = load_entries(&layout)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.id)
+            .collect();
+        assert_eq!(ids, vec!["new"]);
+
+        empty_trash(&config, None).unwrap();
+        assert!(load_entries(&layout).unwrap().is_empty());
+
+        fs::remove_dir_all(&tmp).ok();
+    }
+}
@@ -1,17 +1,18 @@
 use anyhow::{Context, Result};
 use std::fs;
 use std::io::{self, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
-use crate::repo::RepoInfo;
+use crate::config::Config;
+use crate::history::{self, History, SortOrder};
+use crate::repo::{RepoInfo, UPSTREAM_REMOTE};
 
 pub struct Worktree {
     pub path: PathBuf,
     #[allow(dead_code)]
     pub head_sha: String,
     pub branch: Option<String>,
-    #[allow(dead_code)]
     pub is_bare: bool,
     #[allow(dead_code)]
     pub is_locked: bool,
@@ -121,7 +122,11 @@ pub fn list_worktrees(repo_info: &RepoInfo) -> Result<()> {
     let worktrees = Worktree::list(&repo_info.main_repo_dir)?;
 
     for wt in worktrees {
-        let branch_name = wt.branch.unwrap_or_else(|| "(detached)".to_string());
+        let branch_name = if wt.is_bare {
+            "(bare)".to_string()
+        } else {
+            wt.branch.unwrap_or_else(|| "(detached)".to_string())
+        };
         println!("{:<20} {}", branch_name, wt.path.display());
     }
 
@@ -152,6 +157,13 @@ pub fn create_worktree(repo_info: &RepoInfo, branch: &str, base: Option<&str>) -
 
     let branch_exists = check_branch_exists(&repo_info.main_repo_dir, branch)?;
 
+    // New branches in a fork start from the upstream's default branch
+    // rather than from whatever the main checkout has.
+    let upstream_base = match (branch_exists, base, &repo_info.upstream_url) {
+        (false, None, Some(_)) => upstream_head(&repo_info.main_repo_dir),
+        _ => None,
+    };
+
     let mut cmd = Command::new("git");
     cmd.arg("worktree").arg("add");
 
@@ -159,11 +171,15 @@ pub fn create_worktree(repo_info: &RepoInfo, branch: &str, base: Option<&str>) -
         cmd.arg("-b").arg(branch);
     }
 
+    if upstream_base.is_some() {
+        cmd.arg("--no-track");
+    }
+
     cmd.arg(&worktree_path);
 
     if branch_exists {
         cmd.arg(branch);
-    } else if let Some(base_branch) = base {
+    } else if let Some(base_branch) = base.or(upstream_base.as_deref()) {
         cmd.arg(base_branch);
     }
 
@@ -188,14 +204,18 @@ pub fn create_worktree(repo_info: &RepoInfo, branch: &str, base: Option<&str>) -
 }
 
 pub fn switch_worktree(
+    config: &Config,
     repo_info: &RepoInfo,
     branch: Option<&str>,
     interactive: bool,
+    sort: SortOrder,
 ) -> Result<()> {
     if interactive {
-        let worktrees = Worktree::list(&repo_info.main_repo_dir)?;
+        let mut worktrees = Worktree::list(&repo_info.main_repo_dir)?;
+        History::open_for_sorting(config).sort(&mut worktrees, sort, |wt| wt.path.as_path());
         let items: Vec<String> = worktrees
             .iter()
+            .filter(|wt| !wt.is_bare)
             .map(|wt| {
                 format!(
                     "{:<20} {}",
@@ -216,6 +236,7 @@ pub fn switch_worktree(
                 .split_whitespace()
                 .nth(1)
                 .ok_or_else(|| anyhow::anyhow!("Failed to parse selection"))?;
+            history::record(config, Path::new(path));
             println!("{}", path);
         }
 
@@ -226,9 +247,10 @@ pub fn switch_worktree(
         let worktrees = Worktree::list(&repo_info.main_repo_dir)?;
         let worktree = worktrees
             .iter()
-            .find(|wt| wt.branch.as_deref() == Some(branch_name))
+            .find(|wt| !wt.is_bare && wt.branch.as_deref() == Some(branch_name))
             .ok_or_else(|| anyhow::anyhow!("Worktree not found: {}", branch_name))?;
 
+        history::record(config, &worktree.path);
         println!("{}", worktree.path.display());
         return Ok(());
     }
@@ -301,11 +323,20 @@ pub fn delete_worktrees(
         let current = get_current_branch(&repo_info.repo_root)?;
         let worktrees = Worktree::list(&repo_info.main_repo_dir)?;
 
+        // In a bare hub the default branch worktree stands in for the main
+        // checkout, so it is kept like the main checkout would be.
+        let hub_branch = if repo_info.is_bare {
+            Some(get_current_branch(&repo_info.main_repo_dir)?)
+        } else {
+            None
+        };
+
         let to_delete: Vec<&Worktree> = worktrees
             .iter()
             .filter(|wt| {
                 !wt.is_bare
                     && wt.branch.as_deref() != Some(&current)
+                    && wt.branch != hub_branch
                     && wt.path != repo_info.repo_root
             })
             .collect();
@@ -317,14 +348,14 @@ pub fn delete_worktrees(
 
         for wt in to_delete {
             let branch_name = wt.branch.as_deref().unwrap_or("unknown");
-            let status = Command::new("git")
-                .args([
-                    "worktree",
-                    "remove",
-                    if force { "--force" } else { "" },
-                    wt.path.to_str().unwrap(),
-                ])
-                .args(vec![wt.path.to_str().unwrap()])
+            let mut cmd = Command::new("git");
+            cmd.args(["worktree", "remove"]);
+            if force {
+                cmd.arg("--force");
+            }
+            cmd.arg(&wt.path);
+
+            let status = cmd
                 .current_dir(&repo_info.main_repo_dir)
                 .status()
                 .context("Failed to remove worktree")?;
@@ -481,6 +512,27 @@ fn get_current_branch(repo_root: &PathBuf) -> Result<String> {
     Ok(String::from_utf8(output.stdout)?.trim().to_string())
 }
 
+/// The upstream's default branch, e.g. `upstream/main`.
+fn upstream_head(repo_dir: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .args([
+            "symbolic-ref",
+            "--short",
+            &format!("refs/remotes/{}/HEAD", UPSTREAM_REMOTE),
+        ])
+        .current_dir(repo_dir)
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    String::from_utf8(output.stdout)
+        .ok()
+        .map(|head| head.trim().to_string())
+}
+
 fn check_branch_exists(repo_root: &std::path::PathBuf, branch: &str) -> Result<bool> {
     use std::process::Stdio;
 
@@ -571,6 +623,25 @@ bare
         assert!(worktrees[0].is_bare);
     }
 
+    #[test]
+    fn test_parse_porcelain_bare_hub() {
+        let input = r#"worktree /src/github.com/poi2/g
+bare
+
+worktree /src/.worktrees/github.com/poi2/g/main
+HEAD a1b2c3d4
+branch refs/heads/main
+
+"#;
+
+        let worktrees = Worktree::parse_porcelain(input).unwrap();
+        assert_eq!(worktrees.len(), 2);
+        assert!(worktrees[0].is_bare);
+        assert_eq!(worktrees[0].branch, None);
+        assert!(!worktrees[1].is_bare);
+        assert_eq!(worktrees[1].branch, Some("main".to_string()));
+    }
+
     #[test]
     fn test_parse_porcelain_locked() {
         let input = r#"worktree /path/to/locked